@@ -3,6 +3,7 @@
 //
 // The real backend reads `dirs::home_dir()` (i.e. $HOME) and expects:
 //   $HOME/.steam/steam/steamapps/libraryfolders.vdf
+//   <library>/steamapps/appmanifest_<appid>.acf
 //   $HOME/.local/share/Paradox Interactive/<Game>/mod/*.mod
 // where each `.mod` descriptor's `path=` points at that mod's content directory.
 // Conflict + achievement detection walk that content dir, so the descriptors must
@@ -12,6 +13,7 @@
 //   cargo run --example gen_mock_home -- ./mock-home   # build it
 //   HOME=$(pwd)/mock-home cargo run --example mock_smoke -p ferrous-mod-manager
 //   HOME=$(pwd)/mock-home cargo run -p app-ui   # real GUI (or: just mock-ui)
+//   cargo run -- --home ./mock-home games         # headless CLI
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -50,6 +52,12 @@ fn main() {
     for m in mods() {
         write_mod(&mod_dir, m);
     }
+    // Applying a collection rewrites the game's existing dlc_load.json.
+    fs::write(
+        mod_dir.with_file_name("dlc_load.json"),
+        "{\"enabled_mods\":[],\"disabled_dlcs\":[]}",
+    )
+    .expect("write dlc_load.json");
 
     let abs = fs::canonicalize(&home).expect("canonicalize mock home");
     println!("Mock $HOME ready at: {}", abs.display());
@@ -61,27 +69,50 @@ fn main() {
     println!("  HOME={} cargo run -p app-ui", abs.display());
 }
 
-/// Two Steam libraries: Stellaris (with mods) plus CK3 to exercise multi-game
-/// detection. The `path` values are absolute and machine-independent on purpose —
-/// detection only reads them to build display paths, it doesn't stat them.
+/// One Steam library inside the mock home holding Stellaris (with mods) plus CK3
+/// to exercise multi-game detection. Detection reads each game's
+/// `appmanifest_<appid>.acf`, so those are written alongside; the game folders
+/// themselves are left empty.
 fn write_libraryfolders_vdf(home: &Path) {
-    let vdf = r#""libraryfolders"
-{
+    let steamapps = home.join("SteamLibrary/steamapps");
+    fs::create_dir_all(&steamapps).expect("create library steamapps dir");
+    let library = fs::canonicalize(home.join("SteamLibrary")).expect("canonicalize library");
+
+    let vdf = format!(
+        r#""libraryfolders"
+{{
 	"0"
-	{
-		"path"		"/games/SteamLibrary"
+	{{
+		"path"		"{}"
 		"label"		""
 		"apps"
-		{
+		{{
 			"281990"		"123456789"
 			"1158310"		"123456789"
-		}
-	}
-}
-"#;
+		}}
+	}}
+}}
+"#,
+        library.display()
+    );
     let vdf_path = home.join(".steam/steam/steamapps");
     fs::create_dir_all(&vdf_path).expect("create steamapps dir");
     fs::write(vdf_path.join("libraryfolders.vdf"), vdf).expect("write vdf");
+
+    for (app_id, name, install_dir) in [
+        (281990, "Stellaris", "Stellaris"),
+        (1158310, "Crusader Kings III", "Crusader Kings III"),
+    ] {
+        let manifest = format!(
+            "\"AppState\"\n{{\n\t\"appid\"\t\t\"{app_id}\"\n\t\"name\"\t\t\"{name}\"\n\t\"StateFlags\"\t\t\"4\"\n\t\"installdir\"\t\t\"{install_dir}\"\n\t\"LastUpdated\"\t\t\"1700000000\"\n\t\"buildid\"\t\t\"1\"\n}}\n"
+        );
+        fs::create_dir_all(steamapps.join("common").join(install_dir)).expect("create game dir");
+        fs::write(
+            steamapps.join(format!("appmanifest_{app_id}.acf")),
+            manifest,
+        )
+        .expect("write app manifest");
+    }
 }
 
 fn write_mod(mod_dir: &Path, m: MockMod) {
@@ -1,7 +1,9 @@
 // Throwaway: drives the real backend pipeline against a mock $HOME so we can
 // confirm the GUI will get sensible data.
 // Run with: HOME=<mockhome> cargo run --example mock_smoke -p ferrous-mod-manager
+use ferrous_mod_manager::games::GameRegistry;
 use ferrous_mod_manager::locations::ModRoots;
+use ferrous_mod_manager::models::ModCollection;
 use ferrous_mod_manager::{achievements, conflict, detector};
 
 fn main() {
@@ -13,10 +15,18 @@ fn main() {
     }
 
     let game = games.first().expect("at least one game");
-    let mods = detector::discover_mods(game);
+    let discovery = detector::discover_mods(game);
+    let mods = discovery.mods;
     println!("\nDiscovered {} mod(s) in {}:", mods.len(), game.game_name);
+    for d in &discovery.diagnostics {
+        println!("  [parse failed    ] {}", d.message);
+    }
 
-    let statuses = achievements::achievement_status_for_mods(&mods, &mod_roots);
+    let registry = GameRegistry::load();
+    let definition = registry
+        .get(game.app_id)
+        .expect("a definition for the game");
+    let statuses = achievements::achievement_status_for_mods(&mods, definition, &mod_roots);
     for (m, s) in mods.iter().zip(&statuses) {
         let name = m.name.as_deref().unwrap_or("<unnamed>");
         if s.compatible {
@@ -32,14 +42,30 @@ fn main() {
     let blockers = statuses.iter().filter(|s| !s.compatible).count();
     println!("\nIronman/achievements this loadout: {blockers} mod(s) disable them");
 
-    let conflicts = conflict::conflict_detection(mods, &mod_roots);
+    // Everything enabled, in discovery order.
+    let mut loadout = ModCollection::new("Smoke");
+    for m in &mods {
+        loadout.add_mod(m.mod_id().to_string());
+    }
+    let report = conflict::conflict_detection(
+        &loadout.mods,
+        &mods,
+        Some(&conflict::vanilla_dir(game)),
+        &mod_roots,
+    );
+    let conflicts = &report.conflicts;
     println!("\n{} file conflict(s):", conflicts.len());
-    for c in &conflicts {
+    for c in conflicts {
         println!(
-            "  - {} : {:?}  [{:?}]",
+            "  - {} : {} wins over {:?}  [{:?}]",
             c.file_path.display(),
-            c.mod_list,
+            c.winner,
+            c.overridden,
             c.category
         );
     }
+    println!(
+        "{} vanilla file override(s)",
+        report.vanilla_overrides.len()
+    );
 }
@@ -1,13 +1,19 @@
 use std::collections::HashMap;
 
 use ferrous_mod_manager::{
+    backups::{Backup, DiffLine},
     collections::{
         create_collection_for_game, delete_collection_for_game,
         load_or_create_collections_for_game, save_collection_for_game,
     },
     dependency::DependencyReport,
+    games::GameRegistry,
     locations::ModRoots,
-    models::{AchievementStatus, DetectedGame, ModCollection, ModConflict, ModDescriptor},
+    models::{
+        AchievementStatus, ConflictReport, DetectedGame, DlcDescriptor, DlcLoadImport,
+        LocalisationReport, ModCollection, ModDescriptor, ModDiscovery, ObjectConflict,
+    },
+    settings::Settings,
 };
 use tauri::Manager;
 
@@ -28,16 +34,27 @@ pub fn run() {
     tauri::Builder::default()
         .invoke_handler(tauri::generate_handler![
             detect_games,
+            load_settings,
+            save_settings,
             detect_mods,
+            detect_dlcs,
             load_collections,
             save_collection,
             delete_collection,
             create_collection,
             detect_mod_conflict,
+            detect_object_conflicts,
+            localisation_report,
             mod_sizes,
             detect_achievement_compatibility,
             apply_mod_collection,
             enable_mod_with_dependencies,
+            write_workshop_stubs,
+            import_launcher_playsets,
+            import_dlc_load,
+            list_backups,
+            diff_backup,
+            restore_backup,
             launch
         ])
         .setup(|app| {
@@ -63,10 +80,33 @@ fn detect_games() -> Result<Vec<DetectedGame>, String> {
 }
 
 #[tauri::command]
-fn detect_mods(game: DetectedGame) -> Vec<ModDescriptor> {
+fn load_settings() -> Settings {
+    Settings::load()
+}
+
+/// Save the extra Steam, Paradox and mod roots. Takes effect on the next
+/// detection. Mod roots widen what may be scanned, so unsafe ones are
+/// refused.
+#[tauri::command]
+fn save_settings(settings: Settings) -> Result<(), String> {
+    settings
+        .validate()
+        .and_then(|()| settings.save())
+        .map_err(|e| e.to_string())
+}
+
+/// Installed mods plus a diagnostic for every descriptor that failed to parse.
+#[tauri::command]
+fn detect_mods(game: DetectedGame) -> ModDiscovery {
     ferrous_mod_manager::detector::discover_mods(&game)
 }
 
+/// DLCs installed with the game, for choosing a collection's DLC selection.
+#[tauri::command]
+fn detect_dlcs(game: DetectedGame) -> Vec<DlcDescriptor> {
+    ferrous_mod_manager::detector::discover_dlcs(&game)
+}
+
 #[tauri::command]
 fn create_collection(game: DetectedGame, name: String) -> Result<ModCollection, String> {
     create_collection_for_game(game.app_id, name).map_err(|e| e.to_string())
@@ -84,6 +124,25 @@ fn load_collections(games: Vec<DetectedGame>) -> HashMap<u32, Vec<ModCollection>
         .collect()
 }
 
+/// Import the Paradox launcher's playsets as collections, saved alongside the
+/// game's own. Re-importing updates them in place.
+#[tauri::command]
+fn import_launcher_playsets(game: DetectedGame) -> Result<Vec<ModCollection>, String> {
+    ferrous_mod_manager::collections::import_launcher_playsets_for_game(&game)
+        .map_err(|e| e.to_string())
+}
+
+/// Pull the game's current `dlc_load.json` (e.g. after changes made in the
+/// official launcher) into a new saved collection. Entries that match no
+/// installed mod are returned alongside it.
+#[tauri::command]
+fn import_dlc_load(game: DetectedGame, name: String) -> Result<DlcLoadImport, String> {
+    let imported = ferrous_mod_manager::collections::collection_from_dlc_load(&game, &name)
+        .map_err(|e| e.to_string())?;
+    save_collection_for_game(game.app_id, &imported.collection).map_err(|e| e.to_string())?;
+    Ok(imported)
+}
+
 #[tauri::command]
 fn save_collection(game: DetectedGame, mod_collection: ModCollection) -> Result<(), String> {
     save_collection_for_game(game.app_id, &mod_collection).map_err(|e| e.to_string())
@@ -94,9 +153,58 @@ fn delete_collection(game: DetectedGame, mod_collection: ModCollection) -> Resul
     delete_collection_for_game(game.app_id, mod_collection.id).map_err(|e| e.to_string())
 }
 
+/// Conflicts between the collection's enabled mods, in its load order, and
+/// the game files they override. The install folder is re-resolved by app id;
+/// if that fails the vanilla layer is skipped.
 #[tauri::command]
-fn detect_mod_conflict(mods: Vec<ModDescriptor>) -> Vec<ModConflict> {
-    ferrous_mod_manager::conflict::conflict_detection(mods, &ModRoots::detect())
+fn detect_mod_conflict(
+    game: DetectedGame,
+    mod_collection: ModCollection,
+    mods: Vec<ModDescriptor>,
+) -> ConflictReport {
+    let vanilla = ferrous_mod_manager::detector::detect_game(game.app_id)
+        .map(|game| ferrous_mod_manager::conflict::vanilla_dir(&game))
+        .ok();
+    ferrous_mod_manager::conflict::conflict_detection(
+        &mod_collection.mods,
+        &mods,
+        vanilla.as_deref(),
+        &ModRoots::detect(),
+    )
+}
+
+/// Objects (traits, events, defines, ...) several enabled mods define, with
+/// the rule (from the game's definition) that decides which one the game uses.
+#[tauri::command]
+fn detect_object_conflicts(
+    game: DetectedGame,
+    mod_collection: ModCollection,
+    mods: Vec<ModDescriptor>,
+) -> Result<Vec<ObjectConflict>, String> {
+    let registry = GameRegistry::load();
+    let definition = registry
+        .get(game.app_id)
+        .ok_or_else(|| format!("No game definition for app id {}", game.app_id))?;
+    Ok(ferrous_mod_manager::conflict::objects::object_conflicts(
+        &mod_collection.mods,
+        &mods,
+        definition,
+        &ModRoots::detect(),
+    ))
+}
+
+/// Localisation keys several enabled mods define, and each mod's translation
+/// coverage.
+#[tauri::command]
+fn localisation_report(
+    mod_collection: ModCollection,
+    mods: Vec<ModDescriptor>,
+) -> LocalisationReport {
+    ferrous_mod_manager::conflict::localisation::localisation_report(
+        &mod_collection.mods,
+        &mods,
+        &ModRoots::detect(),
+    )
 }
 
 /// On-disk size (bytes) of each mod's file tree, keyed by `mod_id`. Mods without
@@ -106,19 +214,65 @@ fn mod_sizes(mods: Vec<ModDescriptor>) -> HashMap<String, u64> {
     ferrous_mod_manager::conflict::mod_sizes(&mods, &ModRoots::detect())
 }
 
+/// Which folders leave the checksum untouched comes from the game's definition.
 #[tauri::command]
-fn detect_achievement_compatibility(mods: Vec<ModDescriptor>) -> Vec<AchievementStatus> {
-    ferrous_mod_manager::achievements::achievement_status_for_mods(&mods, &ModRoots::detect())
+fn detect_achievement_compatibility(
+    game: DetectedGame,
+    mods: Vec<ModDescriptor>,
+) -> Result<Vec<AchievementStatus>, String> {
+    let registry = GameRegistry::load();
+    let definition = registry
+        .get(game.app_id)
+        .ok_or_else(|| format!("No game definition for app id {}", game.app_id))?;
+    Ok(
+        ferrous_mod_manager::achievements::achievement_status_for_mods(
+            &mods,
+            definition,
+            &ModRoots::detect(),
+        ),
+    )
 }
 
 /// The game's data path is re-resolved from the local Steam install by app id;
 /// the frontend-supplied paths in `game` are deliberately not trusted for writes.
+/// Returns the mods the Paradox launcher's playset had to leave out.
 #[tauri::command]
-fn apply_mod_collection(game: DetectedGame, mod_collection: ModCollection) -> Result<(), String> {
+fn apply_mod_collection(
+    game: DetectedGame,
+    mod_collection: ModCollection,
+) -> Result<Vec<String>, String> {
     ferrous_mod_manager::collections::apply_mod_collection_by_app_id(game.app_id, &mod_collection)
         .map_err(|e| e.to_string())
 }
 
+/// Write `mod/ugc_<id>.mod` stubs for downloaded workshop items the Paradox
+/// launcher hasn't registered yet. Paths are re-resolved by app id, like
+/// `apply_mod_collection`.
+#[tauri::command]
+fn write_workshop_stubs(game: DetectedGame) -> Result<Vec<std::path::PathBuf>, String> {
+    ferrous_mod_manager::detector::write_missing_workshop_stubs_by_app_id(game.app_id)
+        .map_err(|e| e.to_string())
+}
+
+/// Snapshots of game files taken before each apply, newest first.
+#[tauri::command]
+fn list_backups(game: DetectedGame) -> Vec<Backup> {
+    ferrous_mod_manager::backups::list_backups(game.app_id)
+}
+
+#[tauri::command]
+fn diff_backup(game: DetectedGame, id: String) -> Result<Vec<DiffLine>, String> {
+    ferrous_mod_manager::backups::diff_backup(&game, &id).map_err(|e| e.to_string())
+}
+
+/// Restore a snapshot, returning the snapshot taken of the file it replaced.
+/// Paths are re-resolved by app id, like `apply_mod_collection`.
+#[tauri::command]
+fn restore_backup(game: DetectedGame, id: String) -> Result<Option<Backup>, String> {
+    ferrous_mod_manager::backups::restore_backup_by_app_id(game.app_id, &id)
+        .map_err(|e| e.to_string())
+}
+
 #[derive(serde::Serialize)]
 struct EnableModOutcome {
     collection: ModCollection,
@@ -1,3 +1,4 @@
+use crate::games::GameDefinition;
 use crate::locations::ModRoots;
 use crate::models::{AchievementStatus, ConflictCategory, ModDescriptor};
 use std::collections::BTreeSet;
@@ -7,12 +8,16 @@ use walkdir::WalkDir;
 /// Determine whether a single mod keeps achievements (and ironman saves) enabled.
 ///
 /// Paradox games disable achievements whenever an active mod changes the gameplay
-/// checksum. Mods that only touch cosmetic content — graphics, sound, and
-/// localisation text — leave the checksum untouched and stay achievement-safe.
-/// Anything touching game data, defines, events, the map, or an unrecognised path
-/// is treated as achievement-disabling. The returned `gameplay_categories` are the
-/// distinct offending categories, so callers can explain *why* achievements break.
-pub fn achievement_status(game_mod: &ModDescriptor, roots: &ModRoots) -> AchievementStatus {
+/// checksum. Mods that only touch the game's `checksum_exempt` folders — by
+/// default graphics, sound, and localisation text — leave the checksum untouched
+/// and stay achievement-safe. Anything else is treated as achievement-disabling.
+/// The returned `gameplay_categories` are the distinct categories of the
+/// offending files, so callers can explain *why* achievements break.
+pub fn achievement_status(
+    game_mod: &ModDescriptor,
+    game: &GameDefinition,
+    roots: &ModRoots,
+) -> AchievementStatus {
     let mod_id = game_mod.mod_id().to_string();
 
     // Descriptor paths are untrusted Workshop content; refuse to walk anything
@@ -38,9 +43,8 @@ pub fn achievement_status(game_mod: &ModDescriptor, roots: &ModRoots) -> Achieve
             if relative == Path::new("descriptor.mod") {
                 continue;
             }
-            let category = ConflictCategory::from_path(relative);
-            if !category.is_achievement_safe() {
-                gameplay_categories.insert(category);
+            if game.affects_checksum(relative) {
+                gameplay_categories.insert(ConflictCategory::from_path(relative));
             }
         }
     }
@@ -55,16 +59,24 @@ pub fn achievement_status(game_mod: &ModDescriptor, roots: &ModRoots) -> Achieve
 /// Classify a batch of mods, preserving input order.
 pub fn achievement_status_for_mods(
     mods: &[ModDescriptor],
+    game: &GameDefinition,
     roots: &ModRoots,
 ) -> Vec<AchievementStatus> {
-    mods.iter().map(|m| achievement_status(m, roots)).collect()
+    mods.iter()
+        .map(|m| achievement_status(m, game, roots))
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::games::GameRegistry;
     use std::path::PathBuf;
 
+    fn stellaris() -> GameDefinition {
+        GameRegistry::embedded().get(281990).unwrap().clone()
+    }
+
     fn fixture_path(rel: &str) -> String {
         let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         path.push(rel);
@@ -87,6 +99,7 @@ mod tests {
             picture: None,
             version: None,
             dependencies: None,
+            ..Default::default()
         }
     }
 
@@ -96,7 +109,7 @@ mod tests {
             "cosmetic",
             Some(fixture_path("tests/fixtures/achievements/cosmetic_mod")),
         );
-        let status = achievement_status(&m, &fixture_roots());
+        let status = achievement_status(&m, &stellaris(), &fixture_roots());
         assert!(
             status.compatible,
             "a mod touching only gfx/sound/localisation should keep achievements"
@@ -112,7 +125,7 @@ mod tests {
             "gameplay",
             Some(fixture_path("tests/fixtures/achievements/gameplay_mod")),
         );
-        let status = achievement_status(&m, &fixture_roots());
+        let status = achievement_status(&m, &stellaris(), &fixture_roots());
         assert!(!status.compatible);
         assert_eq!(status.gameplay_categories, vec![ConflictCategory::GameData]);
     }
@@ -125,13 +138,13 @@ mod tests {
             "cosmetic",
             Some(fixture_path("tests/fixtures/achievements/cosmetic_mod")),
         );
-        assert!(achievement_status(&m, &fixture_roots()).compatible);
+        assert!(achievement_status(&m, &stellaris(), &fixture_roots()).compatible);
     }
 
     #[test]
     fn test_mod_without_path_assumed_incompatible() {
         let m = make_mod("no_path", None);
-        let status = achievement_status(&m, &fixture_roots());
+        let status = achievement_status(&m, &stellaris(), &fixture_roots());
         assert!(!status.compatible);
         assert!(status.gameplay_categories.is_empty());
     }
@@ -141,7 +154,7 @@ mod tests {
         // A path outside the allowed mod roots must not be walked; the mod is
         // treated like one we can't inspect.
         let m = make_mod("evil", Some("/etc".to_string()));
-        let status = achievement_status(&m, &fixture_roots());
+        let status = achievement_status(&m, &stellaris(), &fixture_roots());
         assert!(!status.compatible);
         assert!(status.gameplay_categories.is_empty());
     }
@@ -152,7 +165,7 @@ mod tests {
             "gameplay",
             Some(fixture_path("tests/fixtures/achievements/gameplay_mod")),
         );
-        let status = achievement_status(&m, &fixture_roots());
+        let status = achievement_status(&m, &stellaris(), &fixture_roots());
         assert_eq!(status.mod_id, m.mod_id());
     }
 
@@ -168,9 +181,22 @@ mod tests {
                 Some(fixture_path("tests/fixtures/achievements/cosmetic_mod")),
             ),
         ];
-        let statuses = achievement_status_for_mods(&mods, &fixture_roots());
+        let statuses = achievement_status_for_mods(&mods, &stellaris(), &fixture_roots());
         assert_eq!(statuses.len(), 2);
         assert!(!statuses[0].compatible);
         assert!(statuses[1].compatible);
     }
+
+    #[test]
+    fn test_checksum_exempt_folders_come_from_the_game() {
+        // A game that also exempts `common` keeps achievements for the mod
+        // that breaks them in Stellaris.
+        let m = make_mod(
+            "gameplay",
+            Some(fixture_path("tests/fixtures/achievements/gameplay_mod")),
+        );
+        let mut game = stellaris();
+        game.checksum_exempt.push("common".to_string());
+        assert!(achievement_status(&m, &game, &fixture_roots()).compatible);
+    }
 }
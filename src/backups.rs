@@ -0,0 +1,487 @@
+use crate::errors::{FileOperationError, LauncherDbError};
+use crate::fsutil::same_bytes;
+use crate::locations::backup_dir;
+use crate::models::DetectedGame;
+use rusqlite::{Connection, MAIN_DB, backup::Progress};
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+/// Snapshots kept per backed-up file; older ones are deleted as new ones are
+/// taken.
+pub const MAX_BACKUPS_PER_FILE: usize = 10;
+
+/// One saved copy of a game file, taken just before the manager overwrote it.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct Backup {
+    /// Snapshot folder name: the creation time in Unix milliseconds, with a
+    /// `-<n>` suffix when several are taken in the same millisecond.
+    pub id: String,
+    /// The backed-up file, relative to the game's Paradox data folder.
+    pub file: PathBuf,
+    pub created_ms: u64,
+}
+
+/// A line of [`diff_backup`] output.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub enum DiffLine {
+    Same(String),
+    /// Only in the current file.
+    Added(String),
+    /// Only in the backup.
+    Removed(String),
+    /// The file isn't text (e.g. the launcher's database), so only whether
+    /// it changed is reported.
+    Binary {
+        identical: bool,
+    },
+    /// The files differ in too many lines to diff (see [`MAX_DIFF_CELLS`]);
+    /// their line counts are reported instead.
+    TooLarge {
+        old_lines: usize,
+        new_lines: usize,
+    },
+}
+
+/// Cap on the LCS table `diff_lines` builds for the lines left after the
+/// common start and end are trimmed: about 2000 changed lines on each side.
+const MAX_DIFF_CELLS: usize = 1 << 22;
+
+/// SQLite databases keep recent writes in `-wal`/`-shm` sidecar files, so
+/// they're copied through SQLite's backup API rather than byte for byte.
+fn is_sqlite(relative: &Path) -> bool {
+    relative.extension().is_some_and(|ext| ext == "sqlite")
+}
+
+/// Whether a file looks like binary data rather than text: a NUL byte in its
+/// first block, the same check `git` and `diff` make.
+fn is_binary(path: &Path) -> io::Result<bool> {
+    let mut head = Vec::new();
+    File::open(path)?.take(8000).read_to_end(&mut head)?;
+    Ok(head.contains(&0))
+}
+
+/// Copy `relative` (e.g. `dlc_load.json`) from the game's Paradox data folder
+/// into a new snapshot, then prune that file's oldest snapshots beyond
+/// [`MAX_BACKUPS_PER_FILE`]. Returns `None` when there's nothing to back up
+/// yet.
+pub fn snapshot(
+    game: &DetectedGame,
+    relative: &Path,
+) -> Result<Option<Backup>, FileOperationError> {
+    let backup = copy_to_store(game, relative)?;
+    prune(game.app_id, relative)?;
+    Ok(backup)
+}
+
+/// [`snapshot`] without the pruning.
+fn copy_to_store(
+    game: &DetectedGame,
+    relative: &Path,
+) -> Result<Option<Backup>, FileOperationError> {
+    let source = Path::new(&game.paradox_data_path).join(relative);
+    if !source.is_file() {
+        return Ok(None);
+    }
+    let store = backup_dir(game.app_id);
+    let created_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default();
+    let mut id = format!("{created_ms:013}");
+    let mut n = 0;
+    while store.join(&id).exists() {
+        n += 1;
+        id = format!("{created_ms:013}-{n}");
+    }
+
+    let target = store.join(&id).join(relative);
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if is_sqlite(relative) {
+        Connection::open(&source)
+            .and_then(|db| db.backup(MAIN_DB, &target, None))
+            .map_err(LauncherDbError::from)?;
+    } else {
+        fs::copy(&source, &target)?;
+    }
+    Ok(Some(Backup {
+        id,
+        file: relative.to_path_buf(),
+        created_ms,
+    }))
+}
+
+/// Delete `relative`'s oldest snapshots beyond [`MAX_BACKUPS_PER_FILE`].
+fn prune(app_id: u32, relative: &Path) -> Result<(), FileOperationError> {
+    let store = backup_dir(app_id);
+    let older: Vec<_> = list_backups(app_id)
+        .into_iter()
+        .filter(|b| b.file == relative)
+        .skip(MAX_BACKUPS_PER_FILE)
+        .collect();
+    for backup in older {
+        fs::remove_dir_all(store.join(&backup.id))?;
+    }
+    Ok(())
+}
+
+/// Every snapshot for a game, newest first.
+pub fn list_backups(app_id: u32) -> Vec<Backup> {
+    let store = backup_dir(app_id);
+    let Ok(entries) = fs::read_dir(&store) else {
+        return Vec::new();
+    };
+    let mut backups: Vec<Backup> = entries
+        .flatten()
+        .filter_map(|entry| read_backup(&store, &entry.file_name().to_string_lossy()))
+        .collect();
+    backups.sort_by_key(|b| Reverse((b.created_ms, sequence(&b.id))));
+    backups
+}
+
+/// The `-<n>` suffix of a snapshot id, 0 when there is none.
+fn sequence(id: &str) -> u32 {
+    id.split_once('-')
+        .and_then(|(_, n)| n.parse().ok())
+        .unwrap_or_default()
+}
+
+/// Snapshot ids come from the IPC layer, so only accept the shape we create
+/// rather than letting `..` or a separator reach a path join.
+fn valid_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_digit() || c == '-')
+}
+
+fn read_backup(store: &Path, id: &str) -> Option<Backup> {
+    if !valid_id(id) {
+        return None;
+    }
+    let dir = store.join(id);
+    let file = WalkDir::new(&dir)
+        .into_iter()
+        .flatten()
+        .find(|e| e.file_type().is_file())?;
+    let created_ms = id.split('-').next()?.parse().ok()?;
+    Some(Backup {
+        id: id.to_string(),
+        file: file.path().strip_prefix(&dir).ok()?.to_path_buf(),
+        created_ms,
+    })
+}
+
+fn find_backup(app_id: u32, id: &str) -> Result<(Backup, PathBuf), FileOperationError> {
+    let store = backup_dir(app_id);
+    let backup = read_backup(&store, id)
+        // Only plain relative paths can be restored inside the data folder.
+        .filter(|b| {
+            b.file
+                .components()
+                .all(|c| matches!(c, Component::Normal(_)))
+        })
+        .ok_or_else(|| FileOperationError::BackupNotFound(id.to_string()))?;
+    let path = store.join(id).join(&backup.file);
+    Ok((backup, path))
+}
+
+/// Line diff from a snapshot to the file as it is now. A file that no longer
+/// exists diffs as empty. Binary files diff as a single [`DiffLine::Binary`].
+pub fn diff_backup(game: &DetectedGame, id: &str) -> Result<Vec<DiffLine>, FileOperationError> {
+    let (backup, saved) = find_backup(game.app_id, id)?;
+    let current = Path::new(&game.paradox_data_path).join(&backup.file);
+    if is_binary(&saved)? || (current.exists() && is_binary(&current)?) {
+        return Ok(vec![DiffLine::Binary {
+            identical: same_bytes(&saved, &current),
+        }]);
+    }
+    let old = crate::fsutil::read_to_string_limited(&saved, crate::fsutil::MAX_READ_BYTES)?;
+    let new = if current.exists() {
+        crate::fsutil::read_to_string_limited(&current, crate::fsutil::MAX_READ_BYTES)?
+    } else {
+        String::new()
+    };
+    Ok(diff_lines(&old, &new))
+}
+
+/// Put a snapshot back in place. The file being replaced is snapshotted first,
+/// so a restore can itself be undone; that snapshot is returned, and old
+/// snapshots are only pruned once the restore is done. Databases
+/// are restored through SQLite, so its `-wal` file can't replay stale pages
+/// over the restored ones.
+pub fn restore_backup(game: &DetectedGame, id: &str) -> Result<Option<Backup>, FileOperationError> {
+    let (backup, saved) = find_backup(game.app_id, id)?;
+    let previous = copy_to_store(game, &backup.file)?;
+    let target = Path::new(&game.paradox_data_path).join(&backup.file);
+    if is_sqlite(&backup.file) {
+        Connection::open(&target)
+            .and_then(|mut db| db.restore(MAIN_DB, &saved, None::<fn(Progress)>))
+            .map_err(LauncherDbError::from)?;
+    } else {
+        crate::fsutil::write_atomic(&target, fs::read(&saved)?)?;
+    }
+    prune(game.app_id, &backup.file)?;
+    Ok(previous)
+}
+
+/// [`restore_backup`] for a game identified only by app id, re-resolving its
+/// data folder from the local Steam install for the IPC layer.
+pub fn restore_backup_by_app_id(
+    app_id: u32,
+    id: &str,
+) -> Result<Option<Backup>, FileOperationError> {
+    restore_backup(&crate::detector::detect_game(app_id)?, id)
+}
+
+/// Longest-common-subsequence line diff. Snapshots can be as large as
+/// `MAX_READ_BYTES`, so the lines both files start and end with are matched
+/// first, and the quadratic table is only built for what's left between them
+/// if that stays under [`MAX_DIFF_CELLS`].
+fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old_mid, new_mid) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+    if (old_mid.len() + 1).saturating_mul(new_mid.len() + 1) > MAX_DIFF_CELLS {
+        return vec![DiffLine::TooLarge {
+            old_lines: old.len(),
+            new_lines: new.len(),
+        }];
+    }
+
+    let mut diff: Vec<DiffLine> = old[..prefix]
+        .iter()
+        .map(|l| DiffLine::Same(l.to_string()))
+        .collect();
+    diff_middle(old_mid, new_mid, &mut diff);
+    diff.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|l| DiffLine::Same(l.to_string())),
+    );
+    diff
+}
+
+fn diff_middle(old: &[&str], new: &[&str], diff: &mut Vec<DiffLine>) {
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            diff.push(DiffLine::Same(old[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            diff.push(DiffLine::Removed(old[i].to_string()));
+            i += 1;
+        } else {
+            diff.push(DiffLine::Added(new[j].to_string()));
+            j += 1;
+        }
+    }
+    diff.extend(old[i..].iter().map(|l| DiffLine::Removed(l.to_string())));
+    diff.extend(new[j..].iter().map(|l| DiffLine::Added(l.to_string())));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A game with its own app id, so parallel tests don't share backups.
+    fn scratch_game(name: &str, app_id: u32) -> DetectedGame {
+        let dir = std::env::temp_dir().join(format!("ferrous_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let _ = fs::remove_dir_all(backup_dir(app_id));
+        fs::create_dir_all(&dir).unwrap();
+        DetectedGame::for_test(app_id, dir)
+    }
+
+    fn cleanup(game: &DetectedGame) {
+        let _ = fs::remove_dir_all(&game.paradox_data_path);
+        let _ = fs::remove_dir_all(backup_dir(game.app_id));
+    }
+
+    #[test]
+    fn test_snapshot_diff_and_restore() {
+        let game = scratch_game("backup_restore", 4_294_967_280);
+        let dlc_load = Path::new(&game.paradox_data_path).join("dlc_load.json");
+        assert_eq!(snapshot(&game, Path::new("dlc_load.json")).unwrap(), None);
+
+        fs::write(&dlc_load, "{\n\"enabled_mods\": [\"mod/a.mod\"]\n}\n").unwrap();
+        let backup = snapshot(&game, Path::new("dlc_load.json"))
+            .unwrap()
+            .unwrap();
+        fs::write(&dlc_load, "{\n\"enabled_mods\": [\"mod/b.mod\"]\n}\n").unwrap();
+
+        assert_eq!(list_backups(game.app_id), vec![backup.clone()]);
+        assert_eq!(
+            diff_backup(&game, &backup.id).unwrap(),
+            vec![
+                DiffLine::Same("{".to_string()),
+                DiffLine::Removed("\"enabled_mods\": [\"mod/a.mod\"]".to_string()),
+                DiffLine::Added("\"enabled_mods\": [\"mod/b.mod\"]".to_string()),
+                DiffLine::Same("}".to_string()),
+            ]
+        );
+
+        let undo = restore_backup(&game, &backup.id).unwrap().unwrap();
+        assert!(fs::read_to_string(&dlc_load).unwrap().contains("mod/a.mod"));
+        // The overwritten state was itself saved, newest first.
+        assert_eq!(list_backups(game.app_id)[0], undo);
+        cleanup(&game);
+    }
+
+    #[test]
+    fn test_snapshot_rotates_old_backups() {
+        let game = scratch_game("backup_rotate", 4_294_967_281);
+        fs::write(
+            Path::new(&game.paradox_data_path).join("dlc_load.json"),
+            "{}",
+        )
+        .unwrap();
+        let first = snapshot(&game, Path::new("dlc_load.json"))
+            .unwrap()
+            .unwrap();
+        for _ in 0..MAX_BACKUPS_PER_FILE {
+            snapshot(&game, Path::new("dlc_load.json")).unwrap();
+        }
+        let backups = list_backups(game.app_id);
+        assert_eq!(backups.len(), MAX_BACKUPS_PER_FILE);
+        assert!(!backups.contains(&first));
+
+        // Restoring the oldest snapshot of a full set still has it to read.
+        let oldest = backups.last().unwrap();
+        restore_backup(&game, &oldest.id).unwrap();
+        assert_eq!(list_backups(game.app_id).len(), MAX_BACKUPS_PER_FILE);
+        cleanup(&game);
+    }
+
+    #[test]
+    fn test_restore_rejects_unknown_ids() {
+        let game = scratch_game("backup_unknown", 4_294_967_282);
+        for id in ["../../etc", "", "123"] {
+            assert!(matches!(
+                restore_backup(&game, id),
+                Err(FileOperationError::BackupNotFound(_))
+            ));
+        }
+        cleanup(&game);
+    }
+
+    #[test]
+    fn test_backups_sort_by_time_then_sequence() {
+        let game = scratch_game("backup_order", 4_294_967_279);
+        let store = backup_dir(game.app_id);
+        for id in ["0000000000004", "0000000000005-2", "0000000000005-10"] {
+            fs::create_dir_all(store.join(id)).unwrap();
+            fs::write(store.join(id).join("dlc_load.json"), "{}").unwrap();
+        }
+        let ids: Vec<_> = list_backups(game.app_id)
+            .into_iter()
+            .map(|b| b.id)
+            .collect();
+        assert_eq!(
+            ids,
+            vec!["0000000000005-10", "0000000000005-2", "0000000000004"]
+        );
+        cleanup(&game);
+    }
+
+    #[test]
+    fn test_large_diffs_are_trimmed_or_refused() {
+        let lines = |prefix: &str, n: usize| -> String {
+            (0..n).map(|i| format!("{prefix}{i}\n")).collect()
+        };
+        // One changed line in a long file only diffs that line.
+        let old = lines("line", 100_000);
+        let new = old.replacen("line500\n", "changed\n", 1);
+        let diff = diff_lines(&old, &new);
+        assert_eq!(diff.len(), 100_001);
+        assert_eq!(diff[500], DiffLine::Removed("line500".to_string()));
+        assert_eq!(diff[501], DiffLine::Added("changed".to_string()));
+
+        let diff = diff_lines(&lines("a", 3000), &lines("b", 3000));
+        assert_eq!(
+            diff,
+            vec![DiffLine::TooLarge {
+                old_lines: 3000,
+                new_lines: 3000
+            }]
+        );
+    }
+
+    #[test]
+    fn test_binary_files_diff_as_identical_or_not() {
+        let game = scratch_game("backup_binary", 4_294_967_278);
+        let thumb = Path::new(&game.paradox_data_path).join("thumb.bin");
+        fs::write(&thumb, b"\0\x01\x02").unwrap();
+        let backup = snapshot(&game, Path::new("thumb.bin")).unwrap().unwrap();
+        assert_eq!(
+            diff_backup(&game, &backup.id).unwrap(),
+            vec![DiffLine::Binary { identical: true }]
+        );
+        fs::write(&thumb, b"\0\x01\x03").unwrap();
+        assert_eq!(
+            diff_backup(&game, &backup.id).unwrap(),
+            vec![DiffLine::Binary { identical: false }]
+        );
+        cleanup(&game);
+    }
+
+    #[test]
+    fn test_sqlite_snapshot_includes_wal_and_restores_through_sqlite() {
+        let game = scratch_game("backup_sqlite", 4_294_967_277);
+        let db_path = Path::new(&game.paradox_data_path).join("launcher-v2.sqlite");
+        fs::copy("tests/fixtures/launcher/launcher-v2.sqlite", &db_path).unwrap();
+        let count = |db: &Connection| -> i64 {
+            db.query_row("SELECT COUNT(*) FROM playsets", [], |r| r.get(0))
+                .unwrap()
+        };
+
+        // Keep a connection open so the write stays in the -wal file.
+        let db = Connection::open(&db_path).unwrap();
+        db.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))
+            .unwrap();
+        db.execute(
+            "INSERT INTO playsets (id, name, createdOn) VALUES ('p-wal', 'Wal', datetime('now'))",
+            [],
+        )
+        .unwrap();
+        let before = count(&db);
+        let backup = snapshot(&game, Path::new("launcher-v2.sqlite"))
+            .unwrap()
+            .unwrap();
+        let saved = backup_dir(game.app_id)
+            .join(&backup.id)
+            .join("launcher-v2.sqlite");
+        assert_eq!(count(&Connection::open(&saved).unwrap()), before);
+
+        db.execute("DELETE FROM playsets", []).unwrap();
+        restore_backup(&game, &backup.id).unwrap();
+        assert_eq!(count(&db), before);
+        drop(db);
+        cleanup(&game);
+    }
+}
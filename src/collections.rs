@@ -1,8 +1,9 @@
 use uuid::Uuid;
 
-use crate::errors::FileOperationError;
+use crate::errors::{FileOperationError, LauncherDbError};
+use crate::games::{DescriptorStyle, GameRegistry};
 use crate::locations::game_data_dir;
-use crate::models::{DetectedGame, DlcLoad, ModCollection};
+use crate::models::{DetectedGame, DlcLoad, DlcLoadImport, ModCollection, ModDescriptor};
 use std::fs::{create_dir_all, read_dir};
 use std::path::{Path, PathBuf};
 
@@ -39,17 +40,15 @@ pub fn load_collection_for_game(path: PathBuf) -> Vec<ModCollection> {
         log::warn!("No mod collections found in : {}", path.display());
         return mod_collections;
     };
-    for file in read_result {
-        if let Ok(entry) = file {
-            let load_attempt = ModCollection::load(&entry.path());
-
-            match load_attempt {
-                Ok(mc) => {
-                    mod_collections.push(mc);
-                }
-                Err(e) => {
-                    log::warn!("Unable to load mod collection: {}", e)
-                }
+    for entry in read_result.flatten() {
+        let load_attempt = ModCollection::load(&entry.path());
+
+        match load_attempt {
+            Ok(mc) => {
+                mod_collections.push(mc);
+            }
+            Err(e) => {
+                log::warn!("Unable to load mod collection: {}", e)
             }
         }
     }
@@ -71,22 +70,35 @@ pub fn import_collection_for_game(
     Ok(collection)
 }
 
+/// Import the Paradox launcher's playsets into a game's collection store. Each
+/// playset keeps its launcher id, so importing again updates the same
+/// collections instead of duplicating them.
+pub fn import_launcher_playsets_for_game(
+    game: &DetectedGame,
+) -> Result<Vec<ModCollection>, FileOperationError> {
+    let launcher_db = crate::playsets::launcher_db_path(game, &GameRegistry::load())
+        .ok_or_else(|| LauncherDbError::NoLauncher(game.game_name.clone()))?;
+    let collections = crate::playsets::import_playsets(&launcher_db)?;
+    for collection in &collections {
+        save_collection_for_game(game.app_id, collection)?;
+    }
+    Ok(collections)
+}
+
 pub fn save_collection_for_game(
     app_id: u32,
     mod_collection: &ModCollection,
 ) -> Result<(), FileOperationError> {
-    let path = PathBuf::from(game_data_dir(app_id));
-    if path.exists() {
-        format_save(&mod_collection, &path)?;
-    } else {
-        create_dir_all(game_data_dir(app_id))?;
-        format_save(&mod_collection, &path)?;
+    let path = game_data_dir(app_id);
+    if !path.exists() {
+        create_dir_all(&path)?;
     }
+    format_save(mod_collection, &path)?;
     Ok(())
 }
 
-fn format_save(mod_collection: &ModCollection, path: &PathBuf) -> Result<(), FileOperationError> {
-    mod_collection.save(&path.join(format!("{}.json", mod_collection.id.to_string())))?;
+fn format_save(mod_collection: &ModCollection, path: &Path) -> Result<(), FileOperationError> {
+    mod_collection.save(&path.join(format!("{}.json", mod_collection.id)))?;
     Ok(())
 }
 
@@ -94,7 +106,7 @@ pub fn delete_collection_for_game(
     app_id: u32,
     mod_collection_id: Uuid,
 ) -> Result<(), FileOperationError> {
-    let path = game_data_dir(app_id).join(format!("{}.json", mod_collection_id.to_string()));
+    let path = game_data_dir(app_id).join(format!("{}.json", mod_collection_id));
     std::fs::remove_file(path)?;
     Ok(())
 }
@@ -107,37 +119,158 @@ pub fn delete_collection_for_game(
 pub fn apply_mod_collection_by_app_id(
     app_id: u32,
     mod_collection: &ModCollection,
-) -> Result<(), FileOperationError> {
-    let games = crate::detector::detect_games()?;
-    let game = games
+) -> Result<Vec<String>, FileOperationError> {
+    apply_mod_collection_for_game(&crate::detector::detect_game(app_id)?, mod_collection)
+}
+
+/// The descriptor file `dlc_load.json` should name for each enabled entry, in
+/// collection order. Entries are matched to discovered mods by
+/// `ModDescriptor::mod_id`; any that aren't installed are collected into a
+/// single error so nothing is written for a half-resolvable collection.
+///
+/// Downloaded workshop items the launcher hasn't registered yet have no
+/// `.mod` file to name, so they're named by the stub the launcher would write
+/// and returned alongside, for the caller to write once nothing else can
+/// fail. Mods that can't have a stub, such as those of games that use
+/// `metadata.json`, fail with `NoDescriptor`.
+fn enabled_descriptor_files(
+    game: &DetectedGame,
+    mod_collection: &ModCollection,
+    registry: &GameRegistry,
+) -> Result<(Vec<String>, Vec<ModDescriptor>), FileOperationError> {
+    let installed = crate::detector::discover_mods(game).mods;
+    let enabled: Vec<(&str, Option<&ModDescriptor>)> = mod_collection
+        .mods
+        .iter()
+        .filter(|m| m.enabled)
+        .map(|entry| {
+            let found = installed.iter().find(|m| m.mod_id() == entry.mod_id);
+            (entry.mod_id.as_str(), found)
+        })
+        .collect();
+    let missing: Vec<String> = enabled
         .iter()
-        .find(|g| g.app_id == app_id)
-        .ok_or(FileOperationError::UnknownGame(app_id))?;
-    apply_mod_collection_for_game(game, mod_collection)
+        .filter(|(_, found)| found.is_none())
+        .map(|(mod_id, _)| mod_id.to_string())
+        .collect();
+    if !missing.is_empty() {
+        return Err(FileOperationError::ModsNotInstalled(missing));
+    }
+
+    let stubs_allowed = registry
+        .get(game.app_id)
+        .is_none_or(|d| d.descriptor == DescriptorStyle::ModFile);
+    let mut files = Vec::new();
+    let mut stubs = Vec::new();
+    let mut undescribed = Vec::new();
+    for (mod_id, descriptor) in enabled.into_iter().filter_map(|(id, m)| Some((id, m?))) {
+        match (&descriptor.descriptor_file, &descriptor.remote_file_id) {
+            (Some(file), _) => files.push(file.clone()),
+            (None, Some(item_id)) if stubs_allowed => {
+                files.push(crate::detector::workshop_stub_file(item_id));
+                stubs.push(descriptor.clone());
+            }
+            (None, _) => undescribed.push(mod_id.to_string()),
+        }
+    }
+    if !undescribed.is_empty() {
+        return Err(FileOperationError::NoDescriptor(undescribed));
+    }
+    Ok((files, stubs))
 }
 
+/// Write the collection's enabled mods to `dlc_load.json`, each named by the
+/// `.mod` file it was discovered from, along with its DLC selection if it
+/// manages one. When the game uses the Paradox launcher and its database
+/// exists, the collection then becomes the launcher's active playset so the
+/// launcher doesn't put its own order back on start. Both files are
+/// snapshotted (see `backups`) before they're changed, and the launcher's is
+/// only touched once `dlc_load.json` has been written. Stubs for enabled
+/// workshop items the launcher hasn't registered are written just before
+/// `dlc_load.json`, so a failed apply leaves `mod/` as it was.
+///
+/// Returns the ids of the collection's mods the launcher has no record of,
+/// which its playset had to leave out.
 pub fn apply_mod_collection_for_game(
     game: &DetectedGame,
     mod_collection: &ModCollection,
-) -> Result<(), FileOperationError> {
+) -> Result<Vec<String>, FileOperationError> {
+    let registry = GameRegistry::load();
+    let (enabled_mods, stubs) = enabled_descriptor_files(game, mod_collection, &registry)?;
+
     let data_path = Path::new(&game.paradox_data_path).join("dlc_load.json");
     let dlc_load_contents =
         crate::fsutil::read_to_string_limited(&data_path, crate::fsutil::MAX_READ_BYTES)?;
     let mut dlc_load: DlcLoad = serde_json::from_str(dlc_load_contents.as_str())?;
-    dlc_load.enabled_mods = Vec::new();
-    for md in &mod_collection.mods {
-        if md.enabled {
-            dlc_load
-                .enabled_mods
-                .push(format!("mod/ugc_{}.mod", md.mod_id));
-        }
+    dlc_load.enabled_mods = enabled_mods;
+    if let Some(disabled_dlcs) = &mod_collection.disabled_dlcs {
+        dlc_load.disabled_dlcs = disabled_dlcs.clone();
     }
-
     let dlc_load_contents = serde_json::to_string_pretty(&dlc_load)?;
+    crate::backups::snapshot(game, Path::new("dlc_load.json"))?;
+
+    for descriptor in &stubs {
+        crate::detector::write_workshop_stub(game, descriptor)?;
+    }
     // Atomic replace: a crash mid-write must not leave the game with a
     // truncated dlc_load.json.
     crate::fsutil::write_atomic(&data_path, &dlc_load_contents)?;
-    Ok(())
+
+    let Some(launcher_db) =
+        crate::playsets::launcher_db_path(game, &registry).filter(|db| db.exists())
+    else {
+        return Ok(Vec::new());
+    };
+    crate::backups::snapshot(game, Path::new("launcher-v2.sqlite"))?;
+    Ok(crate::playsets::export_playset(
+        &launcher_db,
+        mod_collection,
+        true,
+    )?)
+}
+
+/// The reverse of `apply_mod_collection_for_game`: read the game's
+/// `dlc_load.json`, as last written by us or the official launcher, into a new
+/// collection named `name`. Each `enabled_mods` entry is matched to the
+/// discovered mod whose descriptor file it names; the order is kept. The
+/// game's `disabled_dlcs` become the collection's DLC selection.
+pub fn collection_from_dlc_load(
+    game: &DetectedGame,
+    name: &str,
+) -> Result<DlcLoadImport, FileOperationError> {
+    let data_path = Path::new(&game.paradox_data_path).join("dlc_load.json");
+    let contents =
+        crate::fsutil::read_to_string_limited(&data_path, crate::fsutil::MAX_READ_BYTES)?;
+    let dlc_load: DlcLoad = serde_json::from_str(&contents)?;
+    let installed = crate::detector::discover_mods(game).mods;
+
+    let mut collection = ModCollection::new(name);
+    collection.disabled_dlcs = Some(dlc_load.disabled_dlcs);
+    let mut unresolved = Vec::new();
+    for entry in dlc_load.enabled_mods {
+        // The launcher writes forward slashes, but hand-edited files on
+        // Windows may not.
+        let wanted = entry.replace('\\', "/");
+        let found = installed
+            .iter()
+            .find(|m| m.descriptor_file.as_deref() == Some(wanted.as_str()));
+        match found {
+            Some(descriptor)
+                if !collection
+                    .mods
+                    .iter()
+                    .any(|m| m.mod_id == descriptor.mod_id()) =>
+            {
+                collection.add_mod(descriptor.mod_id().to_string())
+            }
+            Some(_) => {}
+            None => unresolved.push(entry),
+        }
+    }
+    Ok(DlcLoadImport {
+        collection,
+        unresolved,
+    })
 }
 
 #[cfg(test)]
@@ -198,6 +331,199 @@ mod tests {
         let _ = std::fs::remove_file(&export_path);
     }
 
+    /// A writable copy of the `discover_workshop` data folder with an empty
+    /// `dlc_load.json`.
+    fn scratch_data_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ferrous_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        create_dir_all(dir.join("mod")).unwrap();
+        for entry in read_dir("tests/fixtures/discover_workshop/mod").unwrap() {
+            let entry = entry.unwrap();
+            std::fs::copy(entry.path(), dir.join("mod").join(entry.file_name())).unwrap();
+        }
+        std::fs::write(
+            dir.join("dlc_load.json"),
+            r#"{"enabled_mods":[],"disabled_dlcs":[]}"#,
+        )
+        .unwrap();
+        dir
+    }
+
+    /// Each test uses its own app id so their backups don't mix.
+    fn scratch_game(dir: &Path, app_id: u32) -> DetectedGame {
+        DetectedGame::for_test(app_id, dir)
+    }
+
+    #[test]
+    fn test_apply_writes_discovered_descriptor_files() {
+        let dir = scratch_data_dir("apply_descriptor_files");
+        let game = scratch_game(&dir, 4_294_967_286);
+        let local = detector::discover_mods(&game)
+            .mods
+            .into_iter()
+            .find(|m| m.remote_file_id.is_none())
+            .unwrap();
+
+        let mut collection = ModCollection::new("Mixed");
+        collection.add_mod(local.mod_id().to_string());
+        collection.add_mod("1002".to_string());
+        collection.add_mod("1001".to_string());
+        collection.mods[2].enabled = false;
+        apply_mod_collection_for_game(&game, &collection).unwrap();
+
+        let written: DlcLoad =
+            serde_json::from_str(&std::fs::read_to_string(dir.join("dlc_load.json")).unwrap())
+                .unwrap();
+        assert_eq!(
+            written.enabled_mods,
+            vec!["mod/local.mod", "mod/ugc_1002.mod"]
+        );
+        // No DLC selection in the collection: the game's is left alone.
+        assert!(written.disabled_dlcs.is_empty());
+        // The previous dlc_load.json was kept.
+        let backups = crate::backups::list_backups(game.app_id);
+        assert_eq!(backups[0].file, Path::new("dlc_load.json"));
+        let _ = std::fs::remove_dir_all(&dir);
+        let _ = std::fs::remove_dir_all(crate::locations::backup_dir(game.app_id));
+    }
+
+    #[test]
+    fn test_apply_writes_stubs_for_unregistered_workshop_items() {
+        let dir = scratch_data_dir("apply_unstubbed");
+        // Item 2001 is only in the fake library's workshop content folder,
+        // which is found through the install path and the real app id.
+        let mut game = scratch_game(&dir, 281990);
+        game.install_path = "tests/fixtures/fake_home/library/steamapps/common/Stellaris".into();
+
+        let mut collection = ModCollection::new("Fresh");
+        collection.add_mod("2001".to_string());
+        assert!(apply_mod_collection_for_game(&game, &collection).is_ok());
+        let written: DlcLoad =
+            serde_json::from_str(&std::fs::read_to_string(dir.join("dlc_load.json")).unwrap())
+                .unwrap();
+        assert_eq!(written.enabled_mods, vec!["mod/ugc_2001.mod"]);
+        assert!(dir.join("mod/ugc_2001.mod").is_file());
+        // Only the collection's items get stubs.
+        assert!(!dir.join("mod/ugc_2004.mod").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+        let _ = std::fs::remove_dir_all(crate::locations::backup_dir(game.app_id));
+    }
+
+    #[test]
+    fn test_apply_switches_dlc_selection() {
+        let dir = scratch_data_dir("apply_dlcs");
+        let game = scratch_game(&dir, 4_294_967_284);
+        let read = || -> DlcLoad {
+            serde_json::from_str(&std::fs::read_to_string(dir.join("dlc_load.json")).unwrap())
+                .unwrap()
+        };
+
+        let mut collection = ModCollection::new("No Leviathans");
+        collection.set_dlc_enabled("dlc/dlc004_leviathans/dlc004.dlc", false);
+        apply_mod_collection_for_game(&game, &collection).unwrap();
+        assert_eq!(
+            read().disabled_dlcs,
+            vec!["dlc/dlc004_leviathans/dlc004.dlc"]
+        );
+
+        collection.set_dlc_enabled("dlc/dlc004_leviathans/dlc004.dlc", true);
+        apply_mod_collection_for_game(&game, &collection).unwrap();
+        assert!(read().disabled_dlcs.is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+        let _ = std::fs::remove_dir_all(crate::locations::backup_dir(game.app_id));
+    }
+
+    #[test]
+    fn test_apply_fails_for_mods_not_installed() {
+        let dir = scratch_data_dir("apply_not_installed");
+        let game = scratch_game(&dir, 4_294_967_287);
+        let mut collection = ModCollection::new("Missing");
+        collection.add_mod("1001".to_string());
+        collection.add_mod("424242".to_string());
+
+        let result = apply_mod_collection_for_game(&game, &collection);
+        assert!(
+            matches!(result, Err(FileOperationError::ModsNotInstalled(ref ids)) if ids == &["424242"])
+        );
+        // Nothing is written for a collection that can't be fully resolved.
+        let untouched = std::fs::read_to_string(dir.join("dlc_load.json")).unwrap();
+        assert!(untouched.contains(r#""enabled_mods":[]"#));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_apply_leaves_launcher_alone_when_dlc_load_is_broken() {
+        let dir = scratch_data_dir("apply_broken_dlc_load");
+        let game = scratch_game(&dir, 4_294_967_283);
+        std::fs::copy(
+            "tests/fixtures/launcher/launcher-v2.sqlite",
+            dir.join("launcher-v2.sqlite"),
+        )
+        .unwrap();
+        std::fs::write(dir.join("dlc_load.json"), "{ not json").unwrap();
+
+        let collection = ModCollection::new("Never applied");
+        assert!(apply_mod_collection_for_game(&game, &collection).is_err());
+        let playsets = crate::playsets::import_playsets(&dir.join("launcher-v2.sqlite")).unwrap();
+        assert!(playsets.iter().all(|p| p.id != collection.id));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_failed_apply_writes_no_workshop_stubs() {
+        let dir = scratch_data_dir("apply_broken_no_stubs");
+        let mut game = scratch_game(&dir, 281990);
+        game.install_path = "tests/fixtures/fake_home/library/steamapps/common/Stellaris".into();
+        std::fs::write(dir.join("dlc_load.json"), "{ not json").unwrap();
+        let listing = || {
+            let mut names: Vec<_> = read_dir(dir.join("mod"))
+                .unwrap()
+                .map(|e| e.unwrap().file_name())
+                .collect();
+            names.sort();
+            names
+        };
+        let before = listing();
+
+        let mut collection = ModCollection::new("Fresh");
+        collection.add_mod("2001".to_string());
+        assert!(apply_mod_collection_for_game(&game, &collection).is_err());
+        assert_eq!(listing(), before);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_collection_from_dlc_load_round_trips_apply() {
+        let dir = scratch_data_dir("dlc_load_import");
+        let game = scratch_game(&dir, 4_294_967_288);
+        std::fs::write(
+            dir.join("dlc_load.json"),
+            r#"{"enabled_mods":["mod/ugc_1002.mod","mod\\local.mod","mod/ugc_9999.mod"],"disabled_dlcs":[]}"#,
+        )
+        .unwrap();
+
+        let imported = collection_from_dlc_load(&game, "From launcher").unwrap();
+        assert_eq!(imported.collection.name, "From launcher");
+        assert_eq!(imported.unresolved, vec!["mod/ugc_9999.mod"]);
+        assert_eq!(imported.collection.mods.len(), 2);
+        assert_eq!(imported.collection.mods[0].mod_id, "1002");
+        assert!(imported.collection.mods.iter().all(|m| m.enabled));
+        assert_eq!(imported.collection.disabled_dlcs, Some(vec![]));
+
+        // Applying the imported collection writes back the resolvable entries.
+        apply_mod_collection_for_game(&game, &imported.collection).unwrap();
+        let written: DlcLoad =
+            serde_json::from_str(&std::fs::read_to_string(dir.join("dlc_load.json")).unwrap())
+                .unwrap();
+        assert_eq!(
+            written.enabled_mods,
+            vec!["mod/ugc_1002.mod", "mod/local.mod"]
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+        let _ = std::fs::remove_dir_all(crate::locations::backup_dir(game.app_id));
+    }
+
     #[test]
     #[ignore = "requires a real Steam install: detect_games() reads live Steam paths"]
     fn test_apply_mod_collection_for_game() {
@@ -1,9 +1,21 @@
+use crate::fsutil::same_bytes;
 use crate::locations::ModRoots;
-use crate::models::{ConflictCategory, ModConflict, ModDescriptor};
-use std::collections::HashMap;
+use crate::models::{
+    ConflictCategory, ConflictKind, ConflictReport, ContentOverlap, DetectedGame, ModConflict,
+    ModDescriptor, ModEntry, VanillaOverride,
+};
+use std::collections::{BTreeSet, HashMap};
+use std::hash::{DefaultHasher, Hasher};
+use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 use walkdir::WalkDir;
 
+pub mod localisation;
+pub mod objects;
+#[cfg(test)]
+pub(crate) mod test_support;
+
 /// Total size in bytes of every file under a mod's directory (best-effort;
 /// unreadable entries are skipped). Used to show per-mod and on-disk size.
 /// Callers must pass a path already validated by [`ModRoots::checked_path`].
@@ -34,15 +46,127 @@ pub fn mod_sizes(mods: &[ModDescriptor], roots: &ModRoots) -> HashMap<String, u6
         .collect()
 }
 
-fn scan_mods(mod_list: Vec<ModDescriptor>, roots: &ModRoots) -> HashMap<PathBuf, Vec<String>> {
-    let mut file_map: HashMap<PathBuf, Vec<String>> = HashMap::new();
-    for game_mod in mod_list {
-        let Some(declared_path) = game_mod.path else {
+/// Name a mod is reported under in conflicts: its display name, falling back
+/// to its declared path. Only used for output; two mods may share one.
+fn report_name(game_mod: &ModDescriptor) -> String {
+    game_mod
+        .name
+        .clone()
+        .unwrap_or_else(|| game_mod.mod_id().to_string())
+}
+
+/// The folders each mod's `replace_path` wipes, by load index. Descriptors
+/// written on Windows may use backslashes or a trailing separator.
+fn replaced_folders(mods: &[ModDescriptor]) -> Vec<Vec<PathBuf>> {
+    mods.iter()
+        .map(|m| {
+            m.replace_path
+                .iter()
+                .flatten()
+                .map(|folder| PathBuf::from(folder.replace('\\', "/").trim_matches('/')))
+                .collect()
+        })
+        .collect()
+}
+
+/// Whether one of `folders` is the folder `file_path` sits in. The game only
+/// clears the files directly in a replaced folder, not its subfolders.
+fn wipes(folders: &[PathBuf], file_path: &Path) -> bool {
+    file_path
+        .parent()
+        .is_some_and(|parent| folders.iter().any(|f| f == parent))
+}
+
+/// The game's files directly in `folder` (relative to the `vanilla` content
+/// folder), which a `replace_path` on it deletes.
+fn wiped_vanilla_files(vanilla: &Path, folder: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(vanilla.join(folder)) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_file()))
+        .map(|entry| folder.join(entry.file_name()))
+        .collect()
+}
+
+/// Each mod's checked content directory, by load index. `None` for mods
+/// without a `path` or whose path is outside the mod roots.
+fn mod_dirs(mods: &[ModDescriptor], roots: &ModRoots) -> Vec<Option<PathBuf>> {
+    mods.iter()
+        .map(|m| roots.checked_path(m.path.as_deref()?))
+        .collect()
+}
+
+/// Length and hash of a file's bytes, or `None` if it can't be read. Only
+/// files that overlap are hashed, so large gfx trees that no other mod
+/// touches are never read. The hash only rules copies out; equal hashes are
+/// confirmed with [`same_bytes`].
+fn content_hash(path: &Path) -> Option<(u64, u64)> {
+    let mut file = std::fs::File::open(path).ok()?;
+    let mut hasher = DefaultHasher::new();
+    let mut buf = [0u8; 64 * 1024];
+    let mut len = 0u64;
+    loop {
+        let read = file.read(&mut buf).ok()?;
+        if read == 0 {
+            break;
+        }
+        hasher.write(&buf[..read]);
+        len += read as u64;
+    }
+    Some((len, hasher.finish()))
+}
+
+/// Compare the copies of `relative` shipped by the mods at `mod_list` (load
+/// indexes). Copies that can't be read count as different from everything.
+fn classify_overlap(
+    relative: &Path,
+    mod_list: &[usize],
+    dirs: &[Option<PathBuf>],
+) -> ContentOverlap {
+    let paths: Vec<Option<PathBuf>> = mod_list
+        .iter()
+        .map(|&i| dirs[i].as_ref().map(|dir| dir.join(relative)))
+        .collect();
+    let hashes: Vec<_> = paths
+        .iter()
+        .map(|path| path.as_deref().and_then(content_hash))
+        .collect();
+    let equal = |i: usize, j: usize| {
+        hashes[i].is_some()
+            && hashes[i] == hashes[j]
+            && same_bytes(paths[i].as_deref().unwrap(), paths[j].as_deref().unwrap())
+    };
+    // Each copy's group is the first copy with the same bytes.
+    let mut group: Vec<usize> = Vec::with_capacity(paths.len());
+    for i in 0..paths.len() {
+        let first = (0..i).find(|&j| group[j] == j && equal(j, i)).unwrap_or(i);
+        group.push(first);
+    }
+    let shared = (0..group.len())
+        .filter(|&i| (0..group.len()).any(|j| i != j && group[i] == group[j]))
+        .count();
+    if group.iter().all(|&g| g == 0) {
+        ContentOverlap::Identical
+    } else if shared == 0 {
+        ContentOverlap::Differing
+    } else {
+        ContentOverlap::PartiallyIdentical
+    }
+}
+
+/// Every file the mods ship, with the load indexes of the mods shipping it.
+/// Mods are walked in load order, so each file's list is in load order too.
+fn scan_mods(mod_list: &[ModDescriptor], roots: &ModRoots) -> HashMap<PathBuf, Vec<usize>> {
+    let mut file_map: HashMap<PathBuf, Vec<usize>> = HashMap::new();
+    for (load_index, game_mod) in mod_list.iter().enumerate() {
+        let Some(declared_path) = game_mod.path.as_deref() else {
             continue;
         };
         // Descriptor paths are untrusted Workshop content; only walk them when
         // they resolve inside a known mod directory.
-        let Some(path) = roots.checked_path(&declared_path) else {
+        let Some(path) = roots.checked_path(declared_path) else {
             continue;
         };
         for entry in WalkDir::new(&path)
@@ -56,14 +180,10 @@ fn scan_mods(mod_list: Vec<ModDescriptor>, roots: &ModRoots) -> HashMap<PathBuf,
                 if relative == Path::new("descriptor.mod") {
                     continue;
                 }
-                let mod_name = game_mod
-                    .name
-                    .clone()
-                    .unwrap_or_else(|| declared_path.clone());
                 file_map
                     .entry(relative.to_path_buf())
                     .or_default()
-                    .push(mod_name);
+                    .push(load_index);
             }
         }
     }
@@ -71,27 +191,204 @@ fn scan_mods(mod_list: Vec<ModDescriptor>, roots: &ModRoots) -> HashMap<PathBuf,
     file_map
 }
 
-pub fn conflict_detection(mods: Vec<ModDescriptor>, roots: &ModRoots) -> Vec<ModConflict> {
-    let file_map = scan_mods(mods, roots);
+/// The installed mods a collection's enabled entries load, in entry order.
+/// Entries that aren't installed are skipped.
+fn load_order(entries: &[ModEntry], installed: &[ModDescriptor]) -> Vec<ModDescriptor> {
+    entries
+        .iter()
+        .filter(|entry| entry.enabled)
+        .filter_map(|entry| installed.iter().find(|m| m.mod_id() == entry.mod_id))
+        .cloned()
+        .collect()
+}
+
+/// A conflict over the mods at `mod_list` (load indexes, in load order) won
+/// by the one at `winner`. `names` maps every loaded mod's index to its report
+/// name, so its length is the size of the load order.
+fn conflict(
+    file_path: &Path,
+    mod_list: &[usize],
+    winner: usize,
+    names: &[String],
+    kind: ConflictKind,
+    content: Option<ContentOverlap>,
+) -> ModConflict {
+    ModConflict {
+        file_path: file_path.to_path_buf(),
+        category: ConflictCategory::from_path(file_path),
+        kind,
+        replaced_by: (kind == ConflictKind::DirectoryReplaced).then(|| names[winner].clone()),
+        winner: names[winner].clone(),
+        overridden: mod_list
+            .iter()
+            .filter(|&&i| i != winner)
+            .map(|&i| names[i].clone())
+            .collect(),
+        winner_is_last: winner + 1 == names.len(),
+        content,
+        mod_list: mod_list.iter().map(|&i| names[i].clone()).collect(),
+    }
+}
+
+/// Folder holding the game's own content, which mods override file by file:
+/// the install root, or its `game/` folder for the games that keep content
+/// there (Crusader Kings III, Victoria 3, ...).
+pub fn vanilla_dir(game: &DetectedGame) -> PathBuf {
+    let install = PathBuf::from(&game.install_path);
+    let content = install.join("game");
+    if content.is_dir() { content } else { install }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).ok()?.modified().ok()
+}
+
+/// Detect conflicts between the mods a collection loads: its enabled
+/// `entries`, in order, resolved against the `installed` mods.
+///
+/// Mods overlapping on a file produce a `FileOverride` conflict, won by the
+/// last to load, unless every copy is byte-identical: then nothing is lost
+/// whatever the order, so it isn't reported. A mod whose descriptor lists a
+/// `replace_path` wipes the files directly in that folder (not its
+/// subfolders) before loading, so earlier mods' files there never reach the
+/// game: that is reported once per folder as a
+/// `DirectoryReplaced` conflict naming the erased mods, and the erased copies
+/// are left out of the folder's file-level conflicts.
+///
+/// With a `vanilla` content folder (see [`vanilla_dir`]), the game's own files
+/// are the layer below every mod: each mod file that shadows one is listed in
+/// `vanilla_overrides`, flagged when the game's copy is the newer of the two.
+/// Game files in a folder a `replace_path` wipes are listed too, as `wiped`
+/// by the first mod replacing it, unless that mod ships its own copy.
+pub fn conflict_detection(
+    entries: &[ModEntry],
+    installed: &[ModDescriptor],
+    vanilla: Option<&Path>,
+    roots: &ModRoots,
+) -> ConflictReport {
+    conflicts_in_load_order(load_order(entries, installed), vanilla, roots)
+}
+
+fn conflicts_in_load_order(
+    mods: Vec<ModDescriptor>,
+    vanilla: Option<&Path>,
+    roots: &ModRoots,
+) -> ConflictReport {
+    let replacements = replaced_folders(&mods);
+    let names: Vec<String> = mods.iter().map(report_name).collect();
+    let dirs = mod_dirs(&mods, roots);
+    let file_map = scan_mods(&mods, roots);
+    let mut vanilla_overrides = Vec::new();
     let mut list_of_conflicts: Vec<ModConflict> = Vec::new();
-    for (file_path, mod_list) in file_map {
-        if mod_list.len() > 1 {
-            let mod_category = ConflictCategory::from_path(&file_path);
-            list_of_conflicts.push(ModConflict {
+    for (file_path, mod_list) in &file_map {
+        // Copies loaded before the last replacing mod are already erased.
+        let surviving = match mod_list
+            .iter()
+            .rposition(|&i| wipes(&replacements[i], file_path))
+        {
+            Some(idx) => &mod_list[idx..],
+            None => &mod_list[..],
+        };
+        if let Some(vanilla_file) = vanilla.map(|dir| dir.join(file_path))
+            && vanilla_file.is_file()
+        {
+            let vanilla_modified = modified(&vanilla_file);
+            for &i in surviving {
+                let mod_modified = dirs[i].as_ref().and_then(|d| modified(&d.join(file_path)));
+                vanilla_overrides.push(VanillaOverride {
+                    file_path: file_path.clone(),
+                    category: ConflictCategory::from_path(file_path),
+                    mod_name: names[i].clone(),
+                    vanilla_newer: vanilla_modified
+                        .zip(mod_modified)
+                        .is_some_and(|(v, m)| v > m),
+                    wiped: false,
+                });
+            }
+        }
+        if let [.., winner] = *surviving
+            && surviving.len() > 1
+        {
+            let content = classify_overlap(file_path, surviving, &dirs);
+            if content == ContentOverlap::Identical {
+                continue;
+            }
+            list_of_conflicts.push(conflict(
                 file_path,
-                mod_list,
-                category: mod_category,
-            });
+                surviving,
+                winner,
+                &names,
+                ConflictKind::FileOverride,
+                Some(content),
+            ));
+        }
+    }
+
+    // Which mods ship files directly in each folder, for the replacements.
+    let mut folder_owners: HashMap<&Path, BTreeSet<usize>> = HashMap::new();
+    for (file_path, mod_list) in &file_map {
+        if let Some(parent) = file_path.parent() {
+            folder_owners
+                .entry(parent)
+                .or_default()
+                .extend(mod_list.iter().copied());
+        }
+    }
+    for (replacer, folders) in replacements.iter().enumerate() {
+        for folder in folders {
+            // An earlier replacement of the same folder already erased
+            // whatever loaded before it.
+            let earlier = replacements[..replacer]
+                .iter()
+                .rposition(|earlier| earlier.contains(folder));
+            if earlier.is_none()
+                && let Some(dir) = vanilla
+            {
+                vanilla_overrides.extend(
+                    wiped_vanilla_files(dir, folder)
+                        .into_iter()
+                        .filter(|file| !file_map.get(file).is_some_and(|o| o.contains(&replacer)))
+                        .map(|file| VanillaOverride {
+                            category: ConflictCategory::from_path(&file),
+                            file_path: file,
+                            mod_name: names[replacer].clone(),
+                            vanilla_newer: false,
+                            wiped: true,
+                        }),
+                );
+            }
+            let first = earlier.unwrap_or(0);
+            let mut mod_list: Vec<usize> = folder_owners
+                .get(folder.as_path())
+                .map(|owners| owners.range(first..replacer).copied().collect())
+                .unwrap_or_default();
+            if mod_list.is_empty() {
+                continue;
+            }
+            mod_list.push(replacer);
+            list_of_conflicts.push(conflict(
+                folder,
+                &mod_list,
+                replacer,
+                &names,
+                ConflictKind::DirectoryReplaced,
+                None,
+            ));
         }
     }
 
-    list_of_conflicts
+    // Within a file, `surviving` is already in load order.
+    vanilla_overrides.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+    ConflictReport {
+        conflicts: list_of_conflicts,
+        vanilla_overrides,
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::ModDescriptor;
+    use crate::models::{ModCollection, ModDescriptor};
     use std::path::PathBuf;
 
     fn fixture_path(name: &str) -> String {
@@ -117,25 +414,28 @@ mod tests {
             picture: None,
             version: None,
             dependencies: None,
+            ..Default::default()
         }
     }
 
     #[test]
     fn test_conflict_detected() {
         let mods = vec![make_mod("mod_a"), make_mod("mod_b")];
-        let file_map = scan_mods(mods, &fixture_roots());
+        let file_map = scan_mods(&mods, &fixture_roots());
 
         let conflict_path = PathBuf::from("common/traits/foo.txt");
         let conflicting_mods = file_map.get(&conflict_path).expect("file should be in map");
-        assert_eq!(conflicting_mods.len(), 2, "foo.txt should be in both mods");
-        assert!(conflicting_mods.contains(&"mod_a".to_string()));
-        assert!(conflicting_mods.contains(&"mod_b".to_string()));
+        assert_eq!(
+            conflicting_mods,
+            &vec![0, 1],
+            "foo.txt should be in both mods"
+        );
     }
 
     #[test]
     fn test_no_conflict_for_unique_files() {
         let mods = vec![make_mod("mod_a"), make_mod("mod_b")];
-        let file_map = scan_mods(mods, &fixture_roots());
+        let file_map = scan_mods(&mods, &fixture_roots());
 
         let unique_path = PathBuf::from("events/my_event.txt");
         let mods_with_file = file_map.get(&unique_path).expect("file should be in map");
@@ -149,32 +449,344 @@ mod tests {
     #[test]
     fn test_conflict_detection_finds_conflicts() {
         let mods = vec![make_mod("mod_a"), make_mod("mod_b")];
-        let conflicts = conflict_detection(mods, &fixture_roots());
+        let conflicts = conflicts_in_load_order(mods, None, &fixture_roots()).conflicts;
 
         let conflict = conflicts
             .iter()
-            .find(|c| c.file_path == PathBuf::from("common/traits/foo.txt"))
+            .find(|c| c.file_path == Path::new("common/traits/foo.txt"))
             .expect("expected a conflict on foo.txt");
 
         assert!(conflict.mod_list.contains(&"mod_a".to_string()));
         assert!(conflict.mod_list.contains(&"mod_b".to_string()));
     }
 
+    #[test]
+    fn test_conflict_detection_follows_collection_order() {
+        let installed = vec![make_mod("mod_a"), make_mod("mod_b")];
+        let mut collection = ModCollection::new("Order");
+        collection.add_mod(installed[1].mod_id().to_string());
+        collection.add_mod(installed[0].mod_id().to_string());
+
+        let conflicts =
+            conflict_detection(&collection.mods, &installed, None, &fixture_roots()).conflicts;
+        let foo = conflicts
+            .iter()
+            .find(|c| c.file_path == Path::new("common/traits/foo.txt"))
+            .expect("expected a conflict on foo.txt");
+        assert_eq!(foo.mod_list, vec!["mod_b", "mod_a"]);
+        assert_eq!(foo.winner, "mod_a");
+        assert_eq!(foo.overridden, vec!["mod_b"]);
+        assert!(foo.winner_is_last);
+
+        // mod_d doesn't ship foo.txt, but loading after mod_a means mod_a is no
+        // longer the last mod loaded.
+        let mod_d = make_mod("mod_d");
+        collection.add_mod(mod_d.mod_id().to_string());
+        let installed = vec![installed[0].clone(), installed[1].clone(), mod_d];
+        let conflicts =
+            conflict_detection(&collection.mods, &installed, None, &fixture_roots()).conflicts;
+        let foo = conflicts
+            .iter()
+            .find(|c| c.file_path == Path::new("common/traits/foo.txt"))
+            .expect("expected a conflict on foo.txt");
+        assert_eq!(foo.winner, "mod_a");
+        assert!(!foo.winner_is_last);
+
+        // Disabling a mod takes it out of the picture entirely.
+        collection.toggle_mod(installed[1].mod_id().to_string());
+        assert!(
+            conflict_detection(&collection.mods, &installed, None, &fixture_roots())
+                .conflicts
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_identical_copies_are_not_conflicts() {
+        // shared_lib.txt is byte-identical in mod_a and mod_b.
+        let mods = vec![make_mod("mod_a"), make_mod("mod_b")];
+        let conflicts = conflicts_in_load_order(mods, None, &fixture_roots()).conflicts;
+        assert!(
+            !conflicts
+                .iter()
+                .any(|c| c.file_path == Path::new("common/scripted_effects/shared_lib.txt"))
+        );
+        let foo = conflicts
+            .iter()
+            .find(|c| c.file_path == Path::new("common/traits/foo.txt"))
+            .unwrap();
+        assert_eq!(foo.content, Some(ContentOverlap::Differing));
+    }
+
+    #[test]
+    fn test_partially_identical_copies() {
+        // mod_c ships the same foo.txt as mod_b; mod_a's differs.
+        let mods = vec![make_mod("mod_a"), make_mod("mod_b"), make_mod("mod_c")];
+        let conflicts = conflicts_in_load_order(mods, None, &fixture_roots()).conflicts;
+        let foo = conflicts
+            .iter()
+            .find(|c| c.file_path == Path::new("common/traits/foo.txt"))
+            .unwrap();
+        assert_eq!(foo.content, Some(ContentOverlap::PartiallyIdentical));
+        assert_eq!(foo.winner, "mod_c");
+    }
+
+    #[test]
+    fn test_vanilla_overrides_flag_newer_game_files() {
+        let vanilla = std::env::temp_dir().join(format!(
+            "ferrous_vanilla_override_test_{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&vanilla);
+        std::fs::create_dir_all(vanilla.join("common/traits")).unwrap();
+        let touch = |relative: &str, mtime: SystemTime| {
+            let path = vanilla.join(relative);
+            std::fs::write(&path, "vanilla = { }").unwrap();
+            let file = std::fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(mtime).unwrap();
+        };
+        // A game patch after the mods were written, and an ancient file.
+        touch(
+            "common/traits/foo.txt",
+            SystemTime::now() + std::time::Duration::from_secs(86_400),
+        );
+        touch("common/traits/bar.txt", SystemTime::UNIX_EPOCH);
+
+        let mods = vec![make_mod("mod_a"), make_mod("mod_b")];
+        let report = conflicts_in_load_order(mods, Some(&vanilla), &fixture_roots());
+        let overrides: Vec<_> = report
+            .vanilla_overrides
+            .iter()
+            .map(|o| {
+                (
+                    o.file_path.to_str().unwrap(),
+                    o.mod_name.as_str(),
+                    o.vanilla_newer,
+                )
+            })
+            .collect();
+        assert_eq!(
+            overrides,
+            vec![
+                ("common/traits/bar.txt", "mod_a", false),
+                ("common/traits/foo.txt", "mod_a", true),
+                ("common/traits/foo.txt", "mod_b", true),
+            ]
+        );
+        // The game's files don't show up as mod-vs-mod conflicts.
+        assert!(
+            report
+                .conflicts
+                .iter()
+                .all(|c| c.file_path != Path::new("common/traits/bar.txt"))
+        );
+        let _ = std::fs::remove_dir_all(&vanilla);
+    }
+
+    #[test]
+    fn test_replace_path_reports_wiped_vanilla_files() {
+        let vanilla =
+            std::env::temp_dir().join(format!("ferrous_vanilla_wiped_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&vanilla);
+        std::fs::create_dir_all(vanilla.join("common/traits/nested")).unwrap();
+        for file in [
+            "common/traits/foo.txt",
+            "common/traits/vanilla_only.txt",
+            "common/traits/nested/kept.txt",
+        ] {
+            std::fs::write(vanilla.join(file), "vanilla = { }").unwrap();
+        }
+
+        let mut mod_b = make_mod("mod_b");
+        mod_b.replace_path = Some(vec!["common/traits".to_string()]);
+        let mut mod_c = make_mod("mod_c");
+        mod_c.replace_path = Some(vec!["common/traits".to_string()]);
+        let report = conflicts_in_load_order(vec![mod_b, mod_c], Some(&vanilla), &fixture_roots());
+        let wiped: Vec<_> = report
+            .vanilla_overrides
+            .iter()
+            .filter(|o| o.wiped)
+            .map(|o| (o.file_path.to_str().unwrap(), o.mod_name.as_str()))
+            .collect();
+        // mod_b ships foo.txt, so that's an ordinary override; subfolders
+        // aren't wiped, and mod_c finds nothing of the game's left.
+        assert_eq!(wiped, vec![("common/traits/vanilla_only.txt", "mod_b")]);
+        let _ = std::fs::remove_dir_all(&vanilla);
+    }
+
+    #[test]
+    fn test_same_named_mods_are_compared_by_their_own_files() {
+        // A local copy and the workshop copy of one mod share a name but not
+        // their files.
+        let mut local = make_mod("mod_a");
+        local.name = Some("Shared".to_string());
+        let mut workshop = make_mod("mod_b");
+        workshop.name = Some("Shared".to_string());
+        let conflicts =
+            conflicts_in_load_order(vec![local, workshop], None, &fixture_roots()).conflicts;
+        let foo = conflicts
+            .iter()
+            .find(|c| c.file_path == Path::new("common/traits/foo.txt"))
+            .expect("expected a conflict on foo.txt");
+        assert_eq!(foo.content, Some(ContentOverlap::Differing));
+        assert_eq!(foo.mod_list, vec!["Shared", "Shared"]);
+        assert_eq!(foo.overridden, vec!["Shared"]);
+    }
+
+    #[test]
+    fn test_same_bytes() {
+        let file = |m: &str, f: &str| PathBuf::from(fixture_path(m)).join(f);
+        let shared = "common/scripted_effects/shared_lib.txt";
+        let foo = "common/traits/foo.txt";
+        assert!(same_bytes(&file("mod_a", shared), &file("mod_b", shared)));
+        assert!(!same_bytes(&file("mod_a", foo), &file("mod_b", foo)));
+        assert!(!same_bytes(&file("mod_a", shared), &file("mod_a", foo)));
+        assert!(!same_bytes(
+            &file("mod_a", "missing.txt"),
+            &file("mod_a", "missing.txt")
+        ));
+    }
+
     #[test]
     fn test_conflict_detection_no_conflicts() {
         let mods = vec![make_mod("mod_a")];
-        let conflicts = conflict_detection(mods, &fixture_roots());
+        let conflicts = conflicts_in_load_order(mods, None, &fixture_roots()).conflicts;
 
         assert!(conflicts.is_empty());
     }
 
+    #[test]
+    fn test_replace_path_reports_erased_directory() {
+        // mod_b loads after mod_a and wipes common/traits, erasing both of
+        // mod_a's trait files.
+        let mut mod_b = make_mod("mod_b");
+        mod_b.replace_path = Some(vec!["common/traits".to_string()]);
+        let conflicts =
+            conflicts_in_load_order(vec![make_mod("mod_a"), mod_b], None, &fixture_roots())
+                .conflicts;
+
+        let replaced = conflicts
+            .iter()
+            .find(|c| c.kind == ConflictKind::DirectoryReplaced)
+            .expect("expected a directory replacement");
+        assert_eq!(replaced.file_path, PathBuf::from("common/traits"));
+        assert_eq!(replaced.mod_list, vec!["mod_a", "mod_b"]);
+        assert_eq!(replaced.replaced_by.as_deref(), Some("mod_b"));
+        assert_eq!(replaced.winner, "mod_b");
+        assert_eq!(replaced.overridden, vec!["mod_a"]);
+        assert_eq!(replaced.category, ConflictCategory::GameData);
+
+        // foo.txt's overlap is subsumed by the folder replacement.
+        assert!(
+            !conflicts
+                .iter()
+                .any(|c| c.file_path == Path::new("common/traits/foo.txt"))
+        );
+    }
+
+    #[test]
+    fn test_second_replacement_only_erases_what_the_first_left() {
+        let mut mod_b = make_mod("mod_b");
+        mod_b.replace_path = Some(vec!["common/traits".to_string()]);
+        let mut mod_c = make_mod("mod_c");
+        mod_c.replace_path = Some(vec!["common/traits".to_string()]);
+        let conflicts = conflicts_in_load_order(
+            vec![make_mod("mod_a"), mod_b, mod_c],
+            None,
+            &fixture_roots(),
+        )
+        .conflicts;
+
+        let replaced: Vec<_> = conflicts
+            .iter()
+            .filter(|c| c.kind == ConflictKind::DirectoryReplaced)
+            .map(|c| (c.winner.as_str(), c.overridden.clone()))
+            .collect();
+        assert_eq!(
+            replaced,
+            vec![
+                ("mod_b", vec!["mod_a".to_string()]),
+                ("mod_c", vec!["mod_b".to_string()])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_replace_path_on_earlier_mod_keeps_file_conflicts() {
+        // A replace_path only erases what loaded before it; mod_b still
+        // overrides mod_a's foo.txt.
+        let mut mod_a = make_mod("mod_a");
+        mod_a.replace_path = Some(vec!["common/traits".to_string()]);
+        let conflicts =
+            conflicts_in_load_order(vec![mod_a, make_mod("mod_b")], None, &fixture_roots())
+                .conflicts;
+
+        assert!(
+            conflicts
+                .iter()
+                .all(|c| c.kind == ConflictKind::FileOverride)
+        );
+        let foo = conflicts
+            .iter()
+            .find(|c| c.file_path == Path::new("common/traits/foo.txt"))
+            .expect("expected a conflict on foo.txt");
+        assert_eq!(foo.mod_list, vec!["mod_a", "mod_b"]);
+    }
+
+    #[test]
+    fn test_replace_path_only_wipes_the_exact_folder() {
+        // Neither a parent folder nor a name prefix of common/traits wipes it,
+        // but Windows-style spellings of the folder itself do.
+        for (folder, wiped) in [
+            ("common", false),
+            ("common/trait", false),
+            ("common\\traits\\", true),
+            ("common/traits/", true),
+        ] {
+            let mut mod_b = make_mod("mod_b");
+            mod_b.replace_path = Some(vec![folder.to_string()]);
+            let conflicts =
+                conflicts_in_load_order(vec![make_mod("mod_a"), mod_b], None, &fixture_roots())
+                    .conflicts;
+            assert_eq!(
+                conflicts
+                    .iter()
+                    .any(|c| c.kind == ConflictKind::DirectoryReplaced),
+                wiped,
+                "replace_path = {folder}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_replace_path_belongs_to_its_own_mod_only() {
+        // Two copies of a mod under one name: only the first has a
+        // replace_path, which must not erase the second's files.
+        let mut first = make_mod("mod_a");
+        first.replace_path = Some(vec!["common/traits".to_string()]);
+        let mut second = make_mod("mod_b");
+        second.name = Some("mod_a".to_string());
+        let conflicts =
+            conflicts_in_load_order(vec![first, second], None, &fixture_roots()).conflicts;
+        assert!(
+            conflicts
+                .iter()
+                .all(|c| c.kind == ConflictKind::FileOverride)
+        );
+        assert!(
+            conflicts
+                .iter()
+                .any(|c| c.file_path == Path::new("common/traits/foo.txt"))
+        );
+    }
+
     #[test]
     fn test_paths_outside_mod_roots_are_not_walked() {
         // A malicious descriptor pointing at the filesystem root (or anywhere
         // outside the allowed mod dirs) must be skipped entirely.
         let mut evil = make_mod("evil");
         evil.path = Some("/".to_string());
-        let file_map = scan_mods(vec![evil], &fixture_roots());
+        let file_map = scan_mods(&[evil], &fixture_roots());
         assert!(file_map.is_empty());
     }
 
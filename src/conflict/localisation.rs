@@ -0,0 +1,248 @@
+use super::{load_order, mod_dirs, report_name};
+use crate::fsutil::{MAX_READ_BYTES, read_text_limited};
+use crate::locations::ModRoots;
+use crate::models::{
+    LanguageCoverage, LocalisationConflict, LocalisationDefinition, LocalisationReport,
+    ModDescriptor, ModEntry, ModLocalisationCoverage,
+};
+use crate::parser::localisation::parse_localisation;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Folders localisation files live under; games use either spelling.
+const LOCALISATION_ROOTS: [&str; 2] = ["localisation", "localization"];
+
+/// One `.yml` file a mod ships.
+struct LocalisationFile {
+    load_index: usize,
+    mod_name: String,
+    file: PathBuf,
+    language: String,
+    entries: Vec<(String, String)>,
+}
+
+impl LocalisationFile {
+    /// Files under a `replace` folder override every other definition.
+    fn is_replace(&self) -> bool {
+        self.file.components().any(|c| c.as_os_str() == "replace")
+    }
+}
+
+/// Every localisation file the mods ship, in load order. Files that don't
+/// parse are logged and skipped.
+fn index_mods(mods: &[ModDescriptor], roots: &ModRoots) -> Vec<LocalisationFile> {
+    let dirs = mod_dirs(mods, roots);
+    let mut files = Vec::new();
+    for (load_index, game_mod) in mods.iter().enumerate() {
+        let mod_name = report_name(game_mod);
+        let Some(dir) = &dirs[load_index] else {
+            continue;
+        };
+        for entry in LOCALISATION_ROOTS
+            .iter()
+            .flat_map(|root| WalkDir::new(dir.join(root)).sort_by_file_name())
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter(|e| e.path().extension().is_some_and(|ext| ext == "yml"))
+        {
+            let Ok(file) = entry.path().strip_prefix(dir) else {
+                continue;
+            };
+            let text = match read_text_limited(entry.path(), MAX_READ_BYTES) {
+                Ok(decoded) => decoded.text,
+                Err(e) => {
+                    log::warn!("Skipping {}: {e}", entry.path().display());
+                    continue;
+                }
+            };
+            match parse_localisation(&text) {
+                Ok(parsed) => files.push(LocalisationFile {
+                    load_index,
+                    mod_name: mod_name.clone(),
+                    file: file.to_path_buf(),
+                    language: parsed.language.to_string(),
+                    entries: parsed
+                        .entries
+                        .iter()
+                        .map(|e| (e.key.to_string(), e.value.to_string()))
+                        .collect(),
+                }),
+                Err(e) => log::warn!("Skipping {}: {e}", entry.path().display()),
+            }
+        }
+    }
+    files
+}
+
+/// Keys several mods define for the same language.
+///
+/// Same-named files replace each other whole (last mod wins). Of what's
+/// left, a definition in a `replace` folder wins; otherwise the first file in
+/// file name order does, whichever mod loads it.
+fn key_conflicts(files: &[LocalisationFile]) -> Vec<LocalisationConflict> {
+    let mut last_copy: HashMap<&Path, usize> = HashMap::new();
+    for file in files {
+        last_copy.insert(file.file.as_path(), file.load_index);
+    }
+
+    let mut by_key: BTreeMap<(&str, &str), Vec<(&LocalisationFile, &str)>> = BTreeMap::new();
+    for file in files {
+        for (key, value) in &file.entries {
+            by_key
+                .entry((file.language.as_str(), key.as_str()))
+                .or_default()
+                .push((file, value.as_str()));
+        }
+    }
+
+    let mut conflicts = Vec::new();
+    for ((language, key), definitions) in by_key {
+        // Mods are told apart by load index, as two of them can share a name.
+        let mut mods: Vec<usize> = definitions.iter().map(|(f, _)| f.load_index).collect();
+        mods.dedup();
+        if mods.len() < 2 {
+            continue;
+        }
+        let Some((winner, winning_value)) = definitions
+            .iter()
+            .filter(|(f, _)| last_copy[f.file.as_path()] == f.load_index)
+            .min_by(|(a, _), (b, _)| {
+                (!a.is_replace(), a.file.file_name()).cmp(&(!b.is_replace(), b.file.file_name()))
+            })
+        else {
+            continue;
+        };
+        conflicts.push(LocalisationConflict {
+            language: language.to_string(),
+            key: key.to_string(),
+            definitions: definitions
+                .iter()
+                .map(|(f, value)| LocalisationDefinition {
+                    mod_name: f.mod_name.clone(),
+                    file: f.file.clone(),
+                    value: value.to_string(),
+                })
+                .collect(),
+            winner: winner.mod_name.clone(),
+            winning_value: winning_value.to_string(),
+        });
+    }
+    conflicts
+}
+
+/// Per-mod coverage of each shipped language against the mod's english keys.
+fn coverage(files: &[LocalisationFile]) -> Vec<ModLocalisationCoverage> {
+    // Keyed by each mod's first file, as two mods can share a name.
+    let mut by_mod: Vec<(&LocalisationFile, BTreeMap<&str, BTreeSet<&str>>)> = Vec::new();
+    for file in files {
+        if by_mod
+            .last()
+            .is_none_or(|(first, _)| first.load_index != file.load_index)
+        {
+            by_mod.push((file, BTreeMap::new()));
+        }
+        let (_, languages) = by_mod.last_mut().expect("pushed above");
+        languages
+            .entry(&file.language)
+            .or_default()
+            .extend(file.entries.iter().map(|(key, _)| key.as_str()));
+    }
+
+    by_mod
+        .into_iter()
+        .map(|(first, languages)| {
+            let english = languages.get("english").cloned().unwrap_or_default();
+            ModLocalisationCoverage {
+                mod_name: first.mod_name.clone(),
+                english_keys: english.len(),
+                languages: languages
+                    .iter()
+                    .filter(|(language, _)| **language != "english")
+                    .map(|(language, keys)| LanguageCoverage {
+                        language: language.to_string(),
+                        missing: english.difference(keys).map(|k| k.to_string()).collect(),
+                    })
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// Localisation keys a collection's enabled mods define more than once across
+/// mods, with the text that wins, and how completely each mod translates its
+/// english text into the other languages it ships.
+///
+/// File-level conflicts only catch mods shipping the same `.yml` file name;
+/// two mods defining one key in differently named files clash just the same.
+pub fn localisation_report(
+    entries: &[ModEntry],
+    installed: &[ModDescriptor],
+    roots: &ModRoots,
+) -> LocalisationReport {
+    report_in_load_order(&load_order(entries, installed), roots)
+}
+
+fn report_in_load_order(mods: &[ModDescriptor], roots: &ModRoots) -> LocalisationReport {
+    let files = index_mods(mods, roots);
+    LocalisationReport {
+        conflicts: key_conflicts(&files),
+        coverage: coverage(&files),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conflict::test_support::{fixture_roots, make_mod, named_mod};
+
+    #[test]
+    fn test_duplicate_keys_across_differently_named_files() {
+        let report =
+            report_in_load_order(&[make_mod("mod_y"), make_mod("mod_x")], &fixture_roots());
+        let strong = report
+            .conflicts
+            .iter()
+            .find(|c| c.key == "trait_strong")
+            .expect("expected a conflict on trait_strong");
+        assert_eq!(strong.language, "english");
+        assert_eq!(strong.definitions.len(), 2);
+        // mod_x's file sorts first, so its text wins despite loading last.
+        assert_eq!(strong.winner, "mod_x");
+        assert_eq!(strong.winning_value, "Strong");
+
+        // A replace folder beats file name order.
+        let desc = report
+            .conflicts
+            .iter()
+            .find(|c| c.key == "trait_strong_desc")
+            .expect("expected a conflict on trait_strong_desc");
+        assert_eq!(desc.winner, "mod_y");
+        assert_eq!(desc.winning_value, "Very strong indeed.");
+    }
+
+    #[test]
+    fn test_language_coverage_against_english() {
+        let report = report_in_load_order(&[make_mod("mod_x")], &fixture_roots());
+        assert!(report.conflicts.is_empty());
+        let [mod_x] = report.coverage.as_slice() else {
+            panic!("expected coverage for mod_x only");
+        };
+        assert_eq!(mod_x.english_keys, 3);
+        let [german] = mod_x.languages.as_slice() else {
+            panic!("expected german only");
+        };
+        assert_eq!(german.language, "german");
+        assert_eq!(german.missing, vec!["trait_strong_desc", "trait_x_only"]);
+    }
+
+    #[test]
+    fn test_same_named_mods_are_kept_apart() {
+        let report = report_in_load_order(
+            &[named_mod("Same", "mod_y"), named_mod("Same", "mod_x")],
+            &fixture_roots(),
+        );
+        assert!(report.conflicts.iter().any(|c| c.key == "trait_strong"));
+        assert_eq!(report.coverage.len(), 2);
+    }
+}
@@ -0,0 +1,312 @@
+use super::{load_order, mod_dirs, report_name};
+use crate::fsutil::{MAX_READ_BYTES, read_text_limited};
+use crate::games::GameDefinition;
+use crate::locations::ModRoots;
+use crate::models::{
+    ConflictCategory, ModDescriptor, ModEntry, ObjectConflict, ObjectDefinition, OverrideRule,
+};
+use crate::parser::script::{self, Block};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Top-level folders whose script files define objects.
+const OBJECT_ROOTS: [&str; 2] = ["common", "events"];
+
+/// Keys of the objects a script file defines. Events are keyed by their `id`
+/// (the block key is just the event type); defines by `Category.NAME`, since
+/// the game merges the category blocks and overrides single values. Scripted
+/// variables (`@name`) are file-local and skipped.
+fn object_keys(folder: &Path, root: &Block) -> Vec<String> {
+    let blocks = root
+        .entries()
+        .filter_map(|entry| Some((entry.key.text(), entry.value.as_block()?)));
+    if folder.starts_with("events") {
+        blocks
+            .filter_map(|(_, block)| block.get("id")?.text().map(|id| id.into_owned()))
+            .collect()
+    } else if folder.starts_with("common/defines") {
+        blocks
+            .flat_map(|(category, block)| {
+                block
+                    .entries()
+                    .map(move |entry| format!("{category}.{}", entry.key.text()))
+            })
+            .collect()
+    } else {
+        root.entries()
+            .map(|entry| entry.key.text())
+            .filter(|key| !key.starts_with('@'))
+            .map(|key| key.into_owned())
+            .collect()
+    }
+}
+
+/// One script file a mod ships, with the objects it defines.
+struct ScriptFile {
+    load_index: usize,
+    mod_name: String,
+    file: PathBuf,
+    keys: Vec<String>,
+}
+
+/// Every object-defining script file the mods ship, grouped by folder and in
+/// load order within each folder. Folders the game merges are left out, and
+/// files that don't parse are logged and skipped.
+fn index_mods(
+    mods: &[ModDescriptor],
+    game: &GameDefinition,
+    roots: &ModRoots,
+) -> BTreeMap<PathBuf, Vec<ScriptFile>> {
+    let dirs = mod_dirs(mods, roots);
+    let mut folders: BTreeMap<PathBuf, Vec<ScriptFile>> = BTreeMap::new();
+    for (load_index, game_mod) in mods.iter().enumerate() {
+        let mod_name = report_name(game_mod);
+        let Some(dir) = &dirs[load_index] else {
+            continue;
+        };
+        for entry in OBJECT_ROOTS
+            .iter()
+            .flat_map(|root| WalkDir::new(dir.join(root)).sort_by_file_name())
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter(|e| e.path().extension().is_some_and(|ext| ext == "txt"))
+        {
+            let Ok(file) = entry.path().strip_prefix(dir) else {
+                continue;
+            };
+            let Some(folder) = file.parent().filter(|f| game.folder_rule(f).is_some()) else {
+                continue;
+            };
+            let text = match read_text_limited(entry.path(), MAX_READ_BYTES) {
+                Ok(decoded) => decoded.text,
+                Err(e) => {
+                    log::warn!("Skipping {}: {e}", entry.path().display());
+                    continue;
+                }
+            };
+            let keys = match script::parse_script(&text) {
+                Ok(parsed) => object_keys(folder, &parsed.root),
+                Err(e) => {
+                    log::warn!("Skipping {}: {e}", entry.path().display());
+                    continue;
+                }
+            };
+            folders
+                .entry(folder.to_path_buf())
+                .or_default()
+                .push(ScriptFile {
+                    load_index,
+                    mod_name: mod_name.clone(),
+                    file: file.to_path_buf(),
+                    keys,
+                });
+        }
+    }
+    folders
+}
+
+/// Key-level conflicts within one folder's files.
+fn folder_conflicts(
+    folder: &Path,
+    files: &[ScriptFile],
+    game: &GameDefinition,
+) -> Vec<ObjectConflict> {
+    let Some(rule) = game.folder_rule(folder) else {
+        return Vec::new();
+    };
+    // Same-named files replace each other whole, so only the last mod's copy
+    // of each file name is read at all.
+    let mut last_copy: HashMap<&Path, usize> = HashMap::new();
+    for file in files {
+        last_copy.insert(file.file.as_path(), file.load_index);
+    }
+    let read = |file: &ScriptFile| last_copy[file.file.as_path()] == file.load_index;
+
+    let mut by_key: BTreeMap<&str, Vec<&ScriptFile>> = BTreeMap::new();
+    for file in files {
+        for key in &file.keys {
+            let defining = by_key.entry(key).or_default();
+            if !defining.iter().any(|f| std::ptr::eq(*f, file)) {
+                defining.push(file);
+            }
+        }
+    }
+
+    let mut conflicts = Vec::new();
+    for (key, defining) in by_key {
+        // Mods are told apart by load index, as two of them can share a name.
+        let mut mods: Vec<&ScriptFile> = defining.clone();
+        mods.dedup_by_key(|f| f.load_index);
+        if mods.len() < 2 {
+            continue;
+        }
+        // The game reads the surviving files in file name order, whichever
+        // mod they came from.
+        let mut surviving: Vec<&ScriptFile> =
+            defining.iter().copied().filter(|f| read(f)).collect();
+        surviving.sort_by(|a, b| a.file.file_name().cmp(&b.file.file_name()));
+        let mut surviving_mods: Vec<usize> = surviving.iter().map(|f| f.load_index).collect();
+        surviving_mods.sort_unstable();
+        surviving_mods.dedup();
+        let (rule, winner) = match (surviving_mods.len(), rule) {
+            // Nothing defining the key survives; the file-level conflict
+            // already covers the replacement.
+            (0, _) => continue,
+            (1, _) => (OverrideRule::FileReplace, surviving[0]),
+            (_, OverrideRule::FirstWins) => (rule, surviving[0]),
+            _ => (rule, surviving[surviving.len() - 1]),
+        };
+        conflicts.push(ObjectConflict {
+            folder: folder.to_path_buf(),
+            key: key.to_string(),
+            category: ConflictCategory::from_path(&winner.file),
+            rule,
+            definitions: defining
+                .iter()
+                .map(|f| ObjectDefinition {
+                    mod_name: f.mod_name.clone(),
+                    file: f.file.clone(),
+                })
+                .collect(),
+            winner: winner.mod_name.clone(),
+            overridden: mods
+                .into_iter()
+                .filter(|m| m.load_index != winner.load_index)
+                .map(|m| m.mod_name.clone())
+                .collect(),
+        });
+    }
+    conflicts
+}
+
+/// Detect objects (traits, events, defines, ...) that more than one of a
+/// collection's enabled mods defines, whatever files they are in.
+///
+/// The game merges `common/` and `events/` folders across mods: files with
+/// the same name replace each other whole (last mod wins), then the rest are
+/// read in file name order and duplicate objects settled by the folder's
+/// rule. So which mod wins depends on file names as much as on load order,
+/// and two mods can clash without sharing a single file. Each folder's rule
+/// comes from the game's `folder_rules`.
+pub fn object_conflicts(
+    entries: &[ModEntry],
+    installed: &[ModDescriptor],
+    game: &GameDefinition,
+    roots: &ModRoots,
+) -> Vec<ObjectConflict> {
+    objects_in_load_order(&load_order(entries, installed), game, roots)
+}
+
+fn objects_in_load_order(
+    mods: &[ModDescriptor],
+    game: &GameDefinition,
+    roots: &ModRoots,
+) -> Vec<ObjectConflict> {
+    index_mods(mods, game, roots)
+        .iter()
+        .flat_map(|(folder, files)| folder_conflicts(folder, files, game))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conflict::test_support::{fixture_roots, make_mod, named_mod};
+    use crate::games::{FolderMerge, FolderRule, GameRegistry};
+
+    fn stellaris() -> GameDefinition {
+        GameRegistry::embedded().get(281990).unwrap().clone()
+    }
+
+    fn find<'a>(conflicts: &'a [ObjectConflict], key: &str) -> &'a ObjectConflict {
+        conflicts
+            .iter()
+            .find(|c| c.key == key)
+            .unwrap_or_else(|| panic!("expected a conflict on {key}"))
+    }
+
+    #[test]
+    fn test_last_wins_follows_file_names_not_load_order() {
+        // mod_y loads first, but its zz_ file sorts after mod_x's 00_ file.
+        let conflicts = objects_in_load_order(
+            &[make_mod("mod_y"), make_mod("mod_x")],
+            &stellaris(),
+            &fixture_roots(),
+        );
+        let strong = find(&conflicts, "trait_strong");
+        assert_eq!(strong.folder, PathBuf::from("common/traits"));
+        assert_eq!(strong.rule, OverrideRule::LastWins);
+        assert_eq!(strong.winner, "mod_y");
+        assert_eq!(strong.overridden, vec!["mod_x"]);
+        assert_eq!(strong.category, ConflictCategory::GameData);
+        assert!(!conflicts.iter().any(|c| c.key == "trait_x_only"));
+    }
+
+    #[test]
+    fn test_events_are_first_wins_by_id() {
+        let conflicts = objects_in_load_order(
+            &[make_mod("mod_x"), make_mod("mod_y")],
+            &stellaris(),
+            &fixture_roots(),
+        );
+        let event = find(&conflicts, "shared.1");
+        assert_eq!(event.rule, OverrideRule::FirstWins);
+        assert_eq!(event.winner, "mod_x");
+        assert_eq!(event.category, ConflictCategory::Events);
+        assert!(!conflicts.iter().any(|c| c.key == "namespace"));
+    }
+
+    #[test]
+    fn test_same_named_files_replace_and_merged_folders_are_skipped() {
+        let conflicts = objects_in_load_order(
+            &[make_mod("mod_x"), make_mod("mod_y")],
+            &stellaris(),
+            &fixture_roots(),
+        );
+        let same = find(&conflicts, "trait_same");
+        assert_eq!(same.rule, OverrideRule::FileReplace);
+        assert_eq!(same.winner, "mod_y");
+
+        // Defines are compared value by value.
+        let define = find(&conflicts, "NGame.START_YEAR");
+        assert_eq!(define.winner, "mod_y");
+        assert!(!conflicts.iter().any(|c| c.key == "NGame.END_YEAR"));
+
+        // on_actions are merged by the game, not overridden.
+        assert!(!conflicts.iter().any(|c| c.key == "on_game_start"));
+    }
+
+    #[test]
+    fn test_folder_rules_come_from_the_game() {
+        let mut game = stellaris();
+        game.folder_rules = vec![FolderRule {
+            folder: "common/traits".to_string(),
+            rule: FolderMerge::Merged,
+        }];
+        let conflicts = objects_in_load_order(
+            &[make_mod("mod_x"), make_mod("mod_y")],
+            &game,
+            &fixture_roots(),
+        );
+        assert!(!conflicts.iter().any(|c| c.key == "trait_strong"));
+        // Without the default rule, events fall back to last wins.
+        let event = find(&conflicts, "shared.1");
+        assert_eq!(event.rule, OverrideRule::LastWins);
+        assert_eq!(event.winner, "mod_y");
+    }
+
+    #[test]
+    fn test_same_named_mods_are_kept_apart() {
+        let conflicts = objects_in_load_order(
+            &[named_mod("Same", "mod_y"), named_mod("Same", "mod_x")],
+            &stellaris(),
+            &fixture_roots(),
+        );
+        let strong = find(&conflicts, "trait_strong");
+        assert_eq!(strong.rule, OverrideRule::LastWins);
+        assert_eq!(strong.winner, "Same");
+        assert_eq!(strong.overridden, vec!["Same"]);
+        assert_eq!(strong.definitions.len(), 2);
+    }
+}
@@ -0,0 +1,26 @@
+use crate::locations::ModRoots;
+use crate::models::ModDescriptor;
+use std::path::PathBuf;
+
+/// The mods the object and localisation tests share.
+fn fixture_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/objects")
+}
+
+pub(crate) fn fixture_roots() -> ModRoots {
+    ModRoots::from_roots([fixture_dir()])
+}
+
+/// The fixture mod in `name`'s folder.
+pub(crate) fn make_mod(name: &str) -> ModDescriptor {
+    named_mod(name, name)
+}
+
+/// The fixture mod in `folder`, going by `name`.
+pub(crate) fn named_mod(name: &str, folder: &str) -> ModDescriptor {
+    ModDescriptor {
+        name: Some(name.to_string()),
+        path: Some(fixture_dir().join(folder).to_string_lossy().into_owned()),
+        ..Default::default()
+    }
+}
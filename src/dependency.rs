@@ -186,6 +186,7 @@ mod tests {
             } else {
                 Some(deps.iter().map(|d| d.to_string()).collect())
             },
+            ..Default::default()
         }
     }
 
@@ -1,135 +1,555 @@
-use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use crate::errors::DetectionError;
-use crate::models::{DetectedGame, ModDescriptor};
-use crate::parser::{mod_descriptor, vdf};
+use crate::errors::{DetectionError, FileOperationError, ModParseError};
+use crate::games::{DescriptorStyle, GameDefinition, GameRegistry};
+use crate::locations::{self, ModRoots};
+use crate::models::{
+    AppManifest, DescriptorDiagnostic, DetectedGame, DlcDescriptor, LibraryVdf, LocationSource,
+    ModDescriptor, ModDiscovery, SourceKind,
+};
+use crate::parser::{appmanifest, appworkshop, dlc_descriptor, metadata, mod_descriptor};
+use crate::settings::Settings;
 
 pub fn detect_games() -> Result<Vec<DetectedGame>, DetectionError> {
     let home = dirs::home_dir().ok_or(DetectionError::NoHomeDir)?;
-    detect_games_from_home(&home)
-}
-
-fn detect_games_from_home(home: &Path) -> Result<Vec<DetectedGame>, DetectionError> {
-    // (game_name, steam_folder, paradox_folder)
-    let game_info: HashMap<u32, (&str, &str, &str)> = HashMap::from([
-        (281990, ("Stellaris", "Stellaris", "Stellaris")),
-        (
-            236850,
-            (
-                "Europa Universalis IV",
-                "Europa Universalis IV",
-                "Europa Universalis IV",
-            ),
-        ),
-        (
-            394360,
-            (
-                "Hearts of Iron IV",
-                "Hearts of Iron IV",
-                "Hearts of Iron IV",
-            ),
-        ),
-        (
-            1158310,
-            (
-                "Crusader Kings III",
-                "Crusader Kings III",
-                "Crusader Kings III",
-            ),
-        ),
-        (529340, ("Victoria 3", "Victoria 3", "Victoria 3")),
-        (
-            859580,
-            ("Imperator: Rome", "Imperator Rome", "Imperator Rome"),
-        ),
-    ]);
-
-    let candidates = crate::locations::steam_library_vdf_candidates(home);
-    // Use the first candidate that exists; otherwise fall back to the first so
-    // the missing-file case still surfaces as a descriptive IO error.
-    let vdf_path = candidates
-        .iter()
-        .find(|p| p.exists())
-        .cloned()
-        .unwrap_or_else(|| {
-            candidates
-                .into_iter()
-                .next()
-                .expect("at least one candidate")
-        });
+    detect_games_from_home(&home, &GameRegistry::load(), &Settings::load())
+}
+
+/// The installed game with `app_id`, re-resolved from the local Steam
+/// install. Entry points that write into a game's folders use this instead of
+/// trusting a `DetectedGame` handed over IPC.
+pub fn detect_game(app_id: u32) -> Result<DetectedGame, FileOperationError> {
+    detect_games()?
+        .into_iter()
+        .find(|g| g.app_id == app_id)
+        .ok_or(FileOperationError::UnknownGame(app_id))
+}
+
+/// Steam libraries from every `libraryfolders.vdf` source, each library once
+/// even when several installs list it. A source that can't be read is logged
+/// and skipped, unless none can be, in which case its error is returned.
+fn steam_libraries(
+    home: &Path,
+    settings: &Settings,
+) -> Result<Vec<(LibraryVdf, LocationSource)>, DetectionError> {
+    let sources = locations::steam_library_vdf_sources(home, settings);
+    if sources.is_empty() {
+        let looked_in: Vec<_> = locations::steam_library_vdf_candidates(home)
+            .iter()
+            .map(|p| p.display().to_string())
+            .collect();
+        return Err(DetectionError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("No Steam libraryfolders.vdf in {}", looked_in.join(", ")),
+        )));
+    }
+
+    let mut libraries = Vec::new();
+    let mut seen = Vec::new();
+    let mut first_error = None;
+    for source in sources {
+        let listed = match locations::read_library_folders(&source.path) {
+            Ok(listed) => listed,
+            Err(e) => {
+                log::warn!("Skipping {}: {e}", source.path.display());
+                first_error.get_or_insert(e);
+                continue;
+            }
+        };
+        for library in listed {
+            let key =
+                fs::canonicalize(&library.path).unwrap_or_else(|_| library.path.clone().into());
+            if !seen.contains(&key) {
+                seen.push(key);
+                libraries.push((library, source.clone()));
+            }
+        }
+    }
+    match first_error {
+        Some(e) if libraries.is_empty() => Err(e),
+        _ => Ok(libraries),
+    }
+}
 
-    let content = crate::fsutil::read_to_string_limited(&vdf_path, crate::fsutil::MAX_READ_BYTES)?;
-    let libraries = vdf::parse_vdf_file(&content)?;
+fn detect_games_from_home(
+    home: &Path,
+    registry: &GameRegistry,
+    settings: &Settings,
+) -> Result<Vec<DetectedGame>, DetectionError> {
+    let libraries = steam_libraries(home, settings)?;
+    let paradox_roots = locations::paradox_data_roots(home, settings);
 
-    let mut detected = Vec::new();
+    let mut detected: Vec<DetectedGame> = Vec::new();
 
-    for library in libraries {
+    for (library, source) in libraries {
+        let steamapps = Path::new(&library.path).join("steamapps");
         for app_id in &library.apps {
-            if let Some(&(game_name, folder_name, paradox_folder)) = game_info.get(app_id) {
-                let install_path = Path::new(&library.path)
-                    .join("steamapps")
-                    .join("common")
-                    .join(folder_name)
-                    .to_string_lossy()
-                    .into_owned();
-
-                let paradox_data_path = crate::locations::paradox_data_root(home)
-                    .join(paradox_folder)
-                    .to_string_lossy()
-                    .into_owned();
-
-                detected.push(DetectedGame {
-                    app_id: *app_id,
-                    install_path,
-                    game_name: game_name.to_string(),
-                    paradox_data_path,
-                });
+            let Some(definition) = registry.get(*app_id) else {
+                continue;
+            };
+            // Two Steam installs can share a game; the first one found wins.
+            if detected.iter().any(|g| g.app_id == *app_id) {
+                continue;
+            }
+            let game_name = &definition.name;
+            // libraryfolders.vdf can lag behind moves and uninstalls; the app
+            // manifest is what Steam itself trusts.
+            let Some(manifest) = read_app_manifest(&steamapps, *app_id) else {
+                continue;
+            };
+            if !manifest.is_installed() {
+                log::info!(
+                    "{game_name} is listed in {} but not installed (StateFlags {})",
+                    library.path,
+                    manifest.state_flags
+                );
+                continue;
             }
+
+            let install_path = install_path(&steamapps, &manifest.install_dir, definition)
+                .to_string_lossy()
+                .into_owned();
+
+            let paradox_data_path = paradox_data_path(&paradox_roots, &definition.paradox_folder)
+                .to_string_lossy()
+                .into_owned();
+
+            detected.push(DetectedGame {
+                app_id: *app_id,
+                install_path,
+                game_name: game_name.clone(),
+                paradox_data_path,
+                state_flags: manifest.state_flags,
+                build_id: manifest.build_id,
+                last_updated: manifest.last_updated,
+                source: Some(source.clone()),
+            });
         }
     }
 
     Ok(detected)
 }
 
-pub fn discover_mods(game: &DetectedGame) -> Vec<ModDescriptor> {
-    let mod_dir = std::path::Path::new(&game.paradox_data_path).join("mod");
+/// The first root (configured ones come first) that already has the game's
+/// data folder, else the default root's, which the game creates on first
+/// start.
+fn paradox_data_path(roots: &[LocationSource], folder: &str) -> PathBuf {
+    roots
+        .iter()
+        .map(|root| root.path.join(folder))
+        .find(|path| path.exists())
+        .unwrap_or_else(|| {
+            roots
+                .iter()
+                .find(|root| root.kind == SourceKind::Default)
+                .expect("paradox_data_roots always lists the default root")
+                .path
+                .join(folder)
+        })
+}
+
+/// The game's folder under `steamapps/common/`. The manifest's `installdir`
+/// is what Steam uses; the definition's default folder is only tried when
+/// that one isn't there.
+fn install_path(steamapps: &Path, install_dir: &str, definition: &GameDefinition) -> PathBuf {
+    let common = steamapps.join("common");
+    [install_dir, definition.steam_folder.as_str()]
+        .into_iter()
+        .map(|folder| common.join(folder))
+        .find(|path| path.is_dir())
+        .unwrap_or_else(|| common.join(install_dir))
+}
+
+/// Read `appmanifest_<app_id>.acf` from a library's `steamapps` folder. A
+/// missing or unreadable manifest means we can't trust the install, so it's
+/// logged and treated as not installed.
+fn read_app_manifest(steamapps: &Path, app_id: u32) -> Option<AppManifest> {
+    let path = steamapps.join(format!("appmanifest_{app_id}.acf"));
+    if !path.exists() {
+        log::info!("No app manifest at {}", path.display());
+        return None;
+    }
+    let parsed = crate::fsutil::read_text_limited(&path, crate::fsutil::MAX_READ_BYTES)
+        .map_err(|e| e.to_string())
+        .and_then(|c| appmanifest::parse_app_manifest(&c.text).map_err(|e| e.to_string()));
+    match parsed {
+        Ok(manifest) => Some(manifest),
+        Err(e) => {
+            log::warn!(
+                "Skipping app {app_id}, bad manifest {}: {e}",
+                path.display()
+            );
+            None
+        }
+    }
+}
+
+/// Parse every mod in the game's `mod/` directory: `.mod` descriptors, and mod
+/// folders carrying a Jomini-style `.metadata/metadata.json`. When both
+/// describe the same folder they're merged, with the `.mod` file winning.
+/// Workshop items the launcher hasn't written a `.mod` stub for yet are added
+/// straight from Steam's workshop content folder. Each mod's `source` is the
+/// mod root its content lives under. Files that can't be read or parsed are
+/// reported in `diagnostics` rather than dropped silently.
+pub fn discover_mods(game: &DetectedGame) -> ModDiscovery {
+    let mut discovery = discover_descriptor_files(game);
+    let unstubbed = unstubbed_workshop_items(game, &mut discovery);
+    discovery.mods.extend(unstubbed);
+    attach_workshop_state(game, &mut discovery);
+    let roots = ModRoots::detect();
+    for m in &mut discovery.mods {
+        m.source = m.path.as_deref().and_then(|p| roots.source_of(p)).cloned();
+    }
+    discovery
+}
+
+/// The DLCs installed with the game: one `dlc/<folder>/<name>.dlc` descriptor
+/// each, sorted by path. Descriptors that can't be read are logged and
+/// skipped; a missing `dlc/` folder just means no DLC.
+pub fn discover_dlcs(game: &DetectedGame) -> Vec<DlcDescriptor> {
+    let dlc_dir = Path::new(&game.install_path).join("dlc");
+    let Ok(folders) = fs::read_dir(&dlc_dir) else {
+        return Vec::new();
+    };
+    let mut dlcs = Vec::new();
+    for folder in folders.flatten() {
+        let Ok(files) = fs::read_dir(folder.path()) else {
+            continue;
+        };
+        for file in files.flatten() {
+            let path = file.path();
+            if path.extension().is_none_or(|ext| ext != "dlc") {
+                continue;
+            }
+            let relative = format!(
+                "dlc/{}/{}",
+                folder.file_name().to_string_lossy(),
+                file.file_name().to_string_lossy()
+            );
+            let parsed = crate::fsutil::read_text_limited(&path, crate::fsutil::MAX_READ_BYTES)
+                .map_err(ModParseError::from)
+                .and_then(|content| dlc_descriptor::parse_dlc_file(&content.text, &relative));
+            match parsed {
+                Ok(dlc) => dlcs.push(dlc),
+                Err(e) => log::warn!("Skipping DLC descriptor {}: {e}", path.display()),
+            }
+        }
+    }
+    dlcs.sort_by(|a, b| a.file.cmp(&b.file));
+    dlcs
+}
+
+/// The mods described by files in the game's `mod/` directory.
+fn discover_descriptor_files(game: &DetectedGame) -> ModDiscovery {
+    let data_dir = Path::new(&game.paradox_data_path);
+    let mod_dir = data_dir.join("mod");
 
     let entries = match fs::read_dir(&mod_dir) {
         Ok(e) => e,
-        Err(_) => return vec![],
+        Err(_) => return ModDiscovery::default(),
     };
 
-    entries
-        .filter_map(|entry| {
-            let path = entry.ok()?.path();
-            if path.extension()? == "mod" {
-                let content =
-                    crate::fsutil::read_to_string_limited(&path, crate::fsutil::MAX_READ_BYTES)
-                        .ok()?;
-                mod_descriptor::parse_mod_file(&content).ok()
-            } else {
-                None
+    let mut discovery = ModDiscovery::default();
+    let mut from_metadata = Vec::new();
+    for path in entries.filter_map(|entry| entry.ok().map(|e| e.path())) {
+        if path.is_dir() {
+            let metadata_file = path.join(".metadata").join("metadata.json");
+            if !metadata_file.is_file() {
+                continue;
             }
-        })
-        .collect()
+            match read_metadata(&metadata_file, &path) {
+                Ok(descriptor) => from_metadata.push((path, descriptor)),
+                Err(e) => report(&mut discovery, metadata_file, e),
+            }
+            continue;
+        }
+        if path.extension().is_none_or(|ext| ext != "mod") {
+            continue;
+        }
+        let parsed = crate::fsutil::read_text_limited(&path, crate::fsutil::MAX_READ_BYTES)
+            .map_err(ModParseError::from)
+            .and_then(|content| mod_descriptor::parse_mod_file(&content.text))
+            .map_err(|e| e.in_file(&path));
+        match parsed {
+            Ok(mut descriptor) => {
+                descriptor.descriptor_file = path
+                    .file_name()
+                    .map(|name| format!("mod/{}", name.to_string_lossy()));
+                discovery.mods.push(descriptor);
+            }
+            Err(e) => report(&mut discovery, path, e),
+        }
+    }
+    for (folder, descriptor) in from_metadata {
+        merge_metadata(&mut discovery.mods, descriptor, &folder, data_dir);
+    }
+    discovery
+}
+
+fn report(discovery: &mut ModDiscovery, file: PathBuf, e: ModParseError) {
+    log::warn!("Skipping mod descriptor {}: {e}", file.display());
+    discovery.diagnostics.push(DescriptorDiagnostic {
+        location: e.location().cloned(),
+        message: e.to_string(),
+        file,
+    });
+}
+
+/// Read `<mod_folder>/.metadata/metadata.json`, pointing the descriptor's
+/// `path` at the mod folder.
+fn read_metadata(metadata_file: &Path, mod_folder: &Path) -> Result<ModDescriptor, ModParseError> {
+    let content = crate::fsutil::read_text_limited(metadata_file, crate::fsutil::MAX_READ_BYTES)?;
+    let mut descriptor = metadata::parse_metadata_json(&content.text)?;
+    descriptor.path = Some(mod_folder.to_string_lossy().into_owned());
+    Ok(descriptor)
+}
+
+/// A descriptor path as a comparable location. Relative paths in `.mod` files
+/// are relative to the game's Paradox data folder.
+fn resolve_mod_path(path: &str, data_dir: &Path) -> PathBuf {
+    let path = data_dir.join(path);
+    path.canonicalize().unwrap_or(path)
+}
+
+/// Fold `folder`'s metadata.json descriptor into the `.mod` descriptor for the
+/// same folder, filling only the fields it leaves unset, or add it as its own
+/// mod.
+fn merge_metadata(
+    mods: &mut Vec<ModDescriptor>,
+    metadata: ModDescriptor,
+    folder: &Path,
+    data_dir: &Path,
+) {
+    let folder = folder
+        .canonicalize()
+        .unwrap_or_else(|_| folder.to_path_buf());
+    let existing = mods.iter_mut().find(|m| {
+        m.path
+            .as_deref()
+            .is_some_and(|p| resolve_mod_path(p, data_dir) == folder)
+    });
+    let Some(existing) = existing else {
+        mods.push(metadata);
+        return;
+    };
+    let ModDescriptor {
+        name,
+        supported_version,
+        tags,
+        version,
+        dependencies,
+        replace_path,
+        metadata_id,
+        ..
+    } = metadata;
+    existing.name = existing.name.take().or(name);
+    existing.supported_version = existing.supported_version.take().or(supported_version);
+    existing.tags = existing.tags.take().or(tags);
+    existing.version = existing.version.take().or(version);
+    existing.dependencies = existing.dependencies.take().or(dependencies);
+    existing.replace_path = existing.replace_path.take().or(replace_path);
+    existing.metadata_id = metadata_id;
+}
+
+/// `<library>/steamapps` for the library the game is installed in. Steam
+/// keeps an app's workshop items in that same library.
+fn library_steamapps(game: &DetectedGame) -> Option<&Path> {
+    let common = Path::new(&game.install_path).parent()?;
+    if common.file_name()? != "common" {
+        return None;
+    }
+    common.parent()
+}
+
+/// `<library>/steamapps/workshop/appworkshop_<appid>.acf`.
+fn app_workshop_path(game: &DetectedGame) -> Option<PathBuf> {
+    Some(
+        library_steamapps(game)?
+            .join("workshop")
+            .join(format!("appworkshop_{}.acf", game.app_id)),
+    )
+}
+
+/// Build the outer descriptor for a downloaded workshop item from the
+/// `descriptor.mod` (or `.metadata/metadata.json`) inside it, the way the
+/// launcher does: absolute `path` to the item, `remote_file_id` from the
+/// folder name.
+fn read_workshop_item(item_dir: &Path, item_id: &str) -> Result<ModDescriptor, ModParseError> {
+    let inner = item_dir.join("descriptor.mod");
+    let mut descriptor = if inner.is_file() {
+        let content = crate::fsutil::read_text_limited(&inner, crate::fsutil::MAX_READ_BYTES)?;
+        mod_descriptor::parse_mod_fields(&content.text).map_err(|e| e.in_file(&inner))?
+    } else {
+        read_metadata(&item_dir.join(".metadata").join("metadata.json"), item_dir)?
+    };
+    descriptor
+        .name
+        .as_ref()
+        .ok_or(ModParseError::MissingField("name".to_string()))?;
+    let absolute = item_dir
+        .canonicalize()
+        .unwrap_or_else(|_| item_dir.to_path_buf());
+    descriptor.path = Some(absolute.to_string_lossy().into_owned());
+    descriptor.remote_file_id = Some(item_id.to_string());
+    Ok(descriptor)
+}
+
+/// Downloaded workshop items in `<library>/steamapps/workshop/content/<appid>/`
+/// that no descriptor in `discovery` already covers. Folders with nothing to
+/// describe them yet (still downloading) are skipped quietly.
+fn unstubbed_workshop_items(
+    game: &DetectedGame,
+    discovery: &mut ModDiscovery,
+) -> Vec<ModDescriptor> {
+    let Some(content_dir) = library_steamapps(game).map(|steamapps| {
+        steamapps
+            .join("workshop")
+            .join("content")
+            .join(game.app_id.to_string())
+    }) else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(&content_dir) else {
+        return Vec::new();
+    };
+
+    let mut items = Vec::new();
+    for item_dir in entries.filter_map(|entry| entry.ok().map(|e| e.path())) {
+        let Some(item_id) = item_dir.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let described = item_dir.join("descriptor.mod").is_file()
+            || item_dir.join(".metadata").join("metadata.json").is_file();
+        if !item_dir.is_dir() || !described {
+            continue;
+        }
+        if discovery
+            .mods
+            .iter()
+            .any(|m| m.remote_file_id.as_deref() == Some(item_id))
+        {
+            continue;
+        }
+        match read_workshop_item(&item_dir, item_id) {
+            Ok(descriptor) => items.push(descriptor),
+            Err(e) => report(discovery, item_dir.clone(), e),
+        }
+    }
+    items
+}
+
+/// Write the `mod/ugc_<id>.mod` stub the Paradox launcher would create for
+/// every downloaded workshop item that doesn't have one, so the game sees
+/// freshly subscribed mods without running the launcher first. Games that
+/// don't use `.mod` descriptors are left alone. Returns the stubs written.
+///
+/// Stubs always carry the fields a `.mod` file requires; an item whose own
+/// descriptor doesn't say which game versions it supports gets `*`.
+pub fn write_missing_workshop_stubs(
+    game: &DetectedGame,
+    registry: &GameRegistry,
+) -> Result<Vec<PathBuf>, FileOperationError> {
+    if registry
+        .get(game.app_id)
+        .is_some_and(|d| d.descriptor != DescriptorStyle::ModFile)
+    {
+        return Ok(Vec::new());
+    }
+    let mut discovery = discover_descriptor_files(game);
+    let mut written = Vec::new();
+    for descriptor in unstubbed_workshop_items(game, &mut discovery) {
+        written.extend(write_workshop_stub(game, &descriptor)?);
+    }
+    Ok(written)
+}
+
+/// Where the Paradox launcher puts a workshop item's `.mod` stub, relative to
+/// the game's data folder (the form `dlc_load.json` names it in).
+pub fn workshop_stub_file(item_id: &str) -> String {
+    format!("mod/ugc_{item_id}.mod")
+}
+
+/// Write the stub for one workshop item (see `write_missing_workshop_stubs`),
+/// unless it already exists. Returns the stub's path if it was written.
+pub fn write_workshop_stub(
+    game: &DetectedGame,
+    descriptor: &ModDescriptor,
+) -> Result<Option<PathBuf>, FileOperationError> {
+    let Some(id) = descriptor.remote_file_id.as_deref() else {
+        return Ok(None);
+    };
+    let stub = Path::new(&game.paradox_data_path).join(workshop_stub_file(id));
+    if stub.exists() {
+        return Ok(None);
+    }
+    let mut descriptor = descriptor.clone();
+    descriptor
+        .supported_version
+        .get_or_insert_with(|| "*".to_string());
+    if let Some(mod_dir) = stub.parent() {
+        fs::create_dir_all(mod_dir)?;
+    }
+    crate::fsutil::write_atomic(&stub, mod_descriptor::serialize_mod_file(&descriptor))?;
+    Ok(Some(stub))
+}
+
+/// Write missing workshop stubs for a game identified only by app id,
+/// re-resolving its paths from the local Steam install rather than trusting
+/// the caller (the IPC entry point).
+pub fn write_missing_workshop_stubs_by_app_id(
+    app_id: u32,
+) -> Result<Vec<PathBuf>, FileOperationError> {
+    write_missing_workshop_stubs(&detect_game(app_id)?, &GameRegistry::load())
+}
+
+/// Fill in `ModDescriptor::workshop` for every workshop mod Steam knows
+/// about. A missing manifest just leaves the field unset; an unreadable one
+/// is reported in `ModDiscovery::workshop_error`.
+fn attach_workshop_state(game: &DetectedGame, discovery: &mut ModDiscovery) {
+    let Some(path) = app_workshop_path(game).filter(|p| p.exists()) else {
+        return;
+    };
+    let parsed = crate::fsutil::read_text_limited(&path, crate::fsutil::MAX_READ_BYTES)
+        .map_err(|e| e.to_string())
+        .and_then(|c| appworkshop::parse_app_workshop(&c.text).map_err(|e| e.to_string()));
+    let items = match parsed {
+        Ok(items) => items,
+        Err(e) => {
+            log::warn!("Could not read workshop state {}: {e}", path.display());
+            discovery.workshop_error = Some(format!("{}: {e}", path.display()));
+            return;
+        }
+    };
+    for descriptor in &mut discovery.mods {
+        descriptor.workshop = descriptor
+            .remote_file_id
+            .as_deref()
+            .and_then(|id| items.iter().find(|item| item.item_id == id))
+            .cloned();
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::WorkshopState;
 
     #[test]
     fn test_detect_games_missing_vdf_returns_io_error() {
-        let result = detect_games_from_home(Path::new("/nonexistent/home"));
+        let result = detect_games_from_home(
+            Path::new("/nonexistent/home"),
+            &GameRegistry::embedded(),
+            &Settings::default(),
+        );
         assert!(matches!(result, Err(DetectionError::Io(_))));
     }
 
     #[test]
     fn test_detect_games_finds_known_games() {
         let home = Path::new("tests/fixtures/fake_home");
-        let games = detect_games_from_home(home).unwrap();
+        let games =
+            detect_games_from_home(home, &GameRegistry::embedded(), &Settings::default()).unwrap();
         assert_eq!(games.len(), 2);
         assert!(games.iter().any(|g| g.game_name == "Stellaris"));
         assert!(games.iter().any(|g| g.game_name == "Crusader Kings III"));
@@ -138,47 +558,161 @@ mod tests {
     #[test]
     fn test_detect_games_ignores_unknown_app_ids() {
         let home = Path::new("tests/fixtures/fake_home_no_games");
-        let games = detect_games_from_home(home).unwrap();
+        let games =
+            detect_games_from_home(home, &GameRegistry::embedded(), &Settings::default()).unwrap();
         assert!(games.is_empty());
     }
 
     #[test]
     fn test_detect_games_populates_paths_correctly() {
         let home = Path::new("tests/fixtures/fake_home");
-        let games = detect_games_from_home(home).unwrap();
+        let games =
+            detect_games_from_home(home, &GameRegistry::embedded(), &Settings::default()).unwrap();
         let stellaris = games.iter().find(|g| g.game_name == "Stellaris").unwrap();
 
         assert_eq!(stellaris.app_id, 281990);
         assert_eq!(
             stellaris.install_path,
-            "/fake/steam/library/steamapps/common/Stellaris"
+            "tests/fixtures/fake_home/library/steamapps/common/Stellaris"
         );
+        assert_eq!(stellaris.build_id, Some(14277519));
+        assert_eq!(stellaris.last_updated, Some(1715180617));
+        assert_eq!(stellaris.state_flags, 4);
         assert_eq!(
             stellaris.paradox_data_path,
             "tests/fixtures/fake_home/.local/share/Paradox Interactive/Stellaris"
         );
     }
 
+    #[test]
+    fn test_detect_games_uses_manifest_installdir() {
+        let home = Path::new("tests/fixtures/fake_home");
+        let games =
+            detect_games_from_home(home, &GameRegistry::embedded(), &Settings::default()).unwrap();
+        let ck3 = games
+            .iter()
+            .find(|g| g.game_name == "Crusader Kings III")
+            .unwrap();
+        // Moved to a folder that doesn't match the game's display name.
+        assert_eq!(
+            ck3.install_path,
+            "tests/fixtures/fake_home/second_library/steamapps/common/CK3"
+        );
+    }
+
+    #[test]
+    fn test_configured_paradox_roots_take_precedence() {
+        let base =
+            std::env::temp_dir().join(format!("ferrous_paradox_roots_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&base);
+        let home = base.join("home");
+        let configured = base.join("configured");
+        fs::create_dir_all(locations::paradox_data_root(&home).join("Stellaris")).unwrap();
+        fs::create_dir_all(configured.join("Stellaris")).unwrap();
+        let settings = Settings {
+            paradox_roots: vec![configured.clone()],
+            ..Default::default()
+        };
+        let roots = locations::paradox_data_roots(&home, &settings);
+
+        assert_eq!(
+            paradox_data_path(&roots, "Stellaris"),
+            configured.join("Stellaris")
+        );
+        // Nowhere yet: the game will create it under the default root.
+        assert_eq!(
+            paradox_data_path(&roots, "Victoria 3"),
+            locations::paradox_data_root(&home).join("Victoria 3")
+        );
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn test_install_path_falls_back_to_the_default_folder() {
+        let steamapps =
+            std::env::temp_dir().join(format!("ferrous_install_path_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&steamapps);
+        let registry = GameRegistry::embedded();
+        let stellaris = registry.get(281990).unwrap();
+
+        // Neither folder exists: the manifest is still trusted.
+        assert_eq!(
+            install_path(&steamapps, "Stellaris Beta", stellaris),
+            steamapps.join("common/Stellaris Beta")
+        );
+        fs::create_dir_all(steamapps.join("common/Stellaris")).unwrap();
+        assert_eq!(
+            install_path(&steamapps, "Stellaris Beta", stellaris),
+            steamapps.join("common/Stellaris")
+        );
+        fs::create_dir_all(steamapps.join("common/Stellaris Beta")).unwrap();
+        assert_eq!(
+            install_path(&steamapps, "Stellaris Beta", stellaris),
+            steamapps.join("common/Stellaris Beta")
+        );
+        let _ = fs::remove_dir_all(&steamapps);
+    }
+
+    #[test]
+    fn test_detect_games_merges_configured_steam_and_paradox_roots() {
+        let settings = Settings {
+            // The broken file is skipped; the extra install lists the default
+            // library again, which must not duplicate Stellaris.
+            steam_roots: vec![
+                PathBuf::from("tests/fixtures/extra_steam/broken.vdf"),
+                PathBuf::from("tests/fixtures/extra_steam"),
+            ],
+            paradox_roots: vec![PathBuf::from("tests/fixtures/extra_paradox")],
+            mod_roots: vec![],
+        };
+        let home = Path::new("tests/fixtures/fake_home");
+        let games = detect_games_from_home(home, &GameRegistry::embedded(), &settings).unwrap();
+        assert_eq!(games.len(), 3);
+
+        let stellaris = games.iter().find(|g| g.app_id == 281990).unwrap();
+        assert_eq!(stellaris.source.as_ref().unwrap().kind, SourceKind::Default);
+
+        let eu4 = games.iter().find(|g| g.app_id == 236850).unwrap();
+        assert_eq!(
+            eu4.source,
+            Some(LocationSource {
+                kind: SourceKind::Settings,
+                path: PathBuf::from("tests/fixtures/extra_steam/steamapps/libraryfolders.vdf"),
+            })
+        );
+        assert_eq!(
+            eu4.paradox_data_path,
+            "tests/fixtures/extra_paradox/Europa Universalis IV"
+        );
+    }
+
+    #[test]
+    fn test_detect_games_skips_uninstalled_and_unmanifested_apps() {
+        // Victoria 3 has no manifest and Hearts of Iron IV's StateFlags lack
+        // the fully-installed bit, though libraryfolders.vdf lists both.
+        let home = Path::new("tests/fixtures/fake_home");
+        let games =
+            detect_games_from_home(home, &GameRegistry::embedded(), &Settings::default()).unwrap();
+        assert!(games.iter().all(|g| g.app_id != 529340));
+        assert!(games.iter().all(|g| g.app_id != 394360));
+    }
+
     fn make_game(paradox_data_path: &str) -> DetectedGame {
-        DetectedGame {
-            app_id: 281990,
-            install_path: String::new(),
-            game_name: "Stellaris".to_string(),
-            paradox_data_path: paradox_data_path.to_string(),
-        }
+        DetectedGame::for_test(281990, paradox_data_path)
     }
 
     #[test]
     fn test_discover_mods_missing_directory_returns_empty() {
         let game = make_game("/nonexistent/path/that/does/not/exist");
-        let mods = discover_mods(&game);
-        assert!(mods.is_empty());
+        let discovery = discover_mods(&game);
+        assert!(discovery.mods.is_empty());
+        assert!(discovery.diagnostics.is_empty());
     }
 
     #[test]
     fn test_discover_mods_returns_valid_mods() {
         let game = make_game("tests/fixtures/discover");
-        let mods = discover_mods(&game);
+        let mods = discover_mods(&game).mods;
         assert_eq!(mods.len(), 1);
         assert_eq!(mods[0].name.as_deref().unwrap(), "Valid Test Mod");
     }
@@ -186,21 +720,246 @@ mod tests {
     #[test]
     fn test_discover_mods_skips_invalid_mod_files() {
         // fixtures/discover/mod/ contains invalid.mod (missing required fields)
-        // it should be silently skipped, not cause a panic or error
+        // it should be skipped, not cause a panic or error
         let game = make_game("tests/fixtures/discover");
-        let mods = discover_mods(&game);
+        let mods = discover_mods(&game).mods;
         assert!(
             mods.iter()
                 .all(|m| m.name.as_deref() != Some("Incomplete Mod"))
         );
     }
 
+    #[test]
+    fn test_discover_mods_reports_invalid_mod_files() {
+        let game = make_game("tests/fixtures/discover");
+        let diagnostics = discover_mods(&game).diagnostics;
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].file.ends_with("invalid.mod"));
+        assert!(diagnostics[0].message.contains("Missing required field"));
+        assert!(diagnostics[0].location.is_none());
+    }
+
+    #[test]
+    fn test_discover_mods_reads_bom_and_windows_1252_descriptors() {
+        let game = make_game("tests/fixtures/discover_encodings");
+        let discovery = discover_mods(&game);
+        assert!(discovery.diagnostics.is_empty());
+        let mut names: Vec<_> = discovery
+            .mods
+            .iter()
+            .map(|m| m.name.as_deref().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["BOM Mod", "Caf\u{e9} Mod"]);
+    }
+
+    #[test]
+    fn test_discover_mods_joins_workshop_state() {
+        let mut game = make_game("tests/fixtures/discover_workshop");
+        game.install_path = "tests/fixtures/fake_home/library/steamapps/common/Stellaris".into();
+        let discovery = discover_mods(&game);
+        let state = |name: &str| {
+            discovery
+                .mods
+                .iter()
+                .find(|m| m.name.as_deref() == Some(name))
+                .unwrap()
+                .workshop
+                .as_ref()
+                .map(|w| w.state)
+        };
+        assert_eq!(state("Up To Date"), Some(WorkshopState::Installed));
+        assert_eq!(state("Stale"), Some(WorkshopState::NeedsUpdate));
+        assert_eq!(state("Local"), None);
+    }
+
+    #[test]
+    fn test_discover_mods_merges_metadata_json() {
+        let discovery = discover_mods(&make_game("tests/fixtures/discover_metadata"));
+        assert_eq!(discovery.mods.len(), 2);
+        assert_eq!(discovery.diagnostics.len(), 1);
+        assert!(
+            discovery.diagnostics[0]
+                .file
+                .ends_with("broken/.metadata/metadata.json")
+        );
+
+        let merged = discovery
+            .mods
+            .iter()
+            .find(|m| m.name.as_deref() == Some("Merged Mod"))
+            .unwrap();
+        // The .mod file's own fields win; metadata fills in the rest.
+        assert_eq!(merged.path.as_deref(), Some("mod/merged"));
+        assert_eq!(merged.supported_version.as_deref(), Some("1.5.*"));
+        assert_eq!(merged.tags, Some(vec!["Gameplay".to_string()]));
+        assert_eq!(merged.metadata_id.as_deref(), Some("merged.mod"));
+
+        let standalone = discovery
+            .mods
+            .iter()
+            .find(|m| m.name.as_deref() == Some("Standalone Mod"))
+            .unwrap();
+        assert!(
+            standalone
+                .path
+                .as_deref()
+                .unwrap()
+                .ends_with("mod/standalone")
+        );
+        assert_eq!(
+            standalone.dependencies,
+            Some(vec!["Merged Mod".to_string()])
+        );
+    }
+
+    fn workshop_game(paradox_data_path: &str) -> DetectedGame {
+        let mut game = make_game(paradox_data_path);
+        game.install_path = "tests/fixtures/fake_home/library/steamapps/common/Stellaris".into();
+        game
+    }
+
+    #[test]
+    fn test_unreadable_workshop_state_is_not_a_descriptor_diagnostic() {
+        let library =
+            std::env::temp_dir().join(format!("ferrous_workshop_error_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&library);
+        fs::create_dir_all(library.join("steamapps/workshop")).unwrap();
+        fs::write(
+            library.join("steamapps/workshop/appworkshop_281990.acf"),
+            "\"AppWorkshop\" {",
+        )
+        .unwrap();
+        let mut game = make_game("tests/fixtures/discover");
+        game.install_path = library
+            .join("steamapps/common/Stellaris")
+            .to_string_lossy()
+            .into_owned();
+
+        let discovery = discover_mods(&game);
+        assert_eq!(discovery.mods.len(), 1);
+        assert_eq!(discovery.diagnostics.len(), 1);
+        assert!(
+            discovery
+                .workshop_error
+                .is_some_and(|e| e.contains("appworkshop_281990.acf"))
+        );
+        let _ = fs::remove_dir_all(&library);
+    }
+
+    #[test]
+    fn test_discover_mods_adds_unstubbed_workshop_content() {
+        // ugc_1001.mod already covers item 1001; 2001 only exists in the
+        // workshop content folder; 2002 is still downloading; 2003 is broken.
+        let discovery = discover_mods(&workshop_game("tests/fixtures/discover_workshop"));
+        let ids: Vec<_> = discovery
+            .mods
+            .iter()
+            .filter_map(|m| m.remote_file_id.as_deref())
+            .collect();
+        assert_eq!(ids.iter().filter(|id| **id == "1001").count(), 1);
+        assert!(!ids.contains(&"2002"));
+
+        let fresh = discovery
+            .mods
+            .iter()
+            .find(|m| m.remote_file_id.as_deref() == Some("2001"))
+            .unwrap();
+        assert_eq!(fresh.name.as_deref(), Some("Fresh Subscription"));
+        let path = Path::new(fresh.path.as_deref().unwrap());
+        assert!(path.is_absolute());
+        assert!(path.ends_with("workshop/content/281990/2001"));
+
+        assert_eq!(discovery.diagnostics.len(), 1);
+        assert!(discovery.diagnostics[0].file.ends_with("281990/2003"));
+    }
+
+    #[test]
+    fn test_write_missing_workshop_stubs() {
+        let data_dir =
+            std::env::temp_dir().join(format!("ferrous_workshop_stubs_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&data_dir);
+        fs::create_dir_all(data_dir.join("mod")).unwrap();
+        fs::write(
+            data_dir.join("mod/ugc_1001.mod"),
+            "name=\"Up To Date\"\npath=\"mod/up_to_date\"\nremote_file_id=\"1001\"\nsupported_version=\"3.*\"\n",
+        )
+        .unwrap();
+
+        let game = workshop_game(data_dir.to_str().unwrap());
+        let mut written = write_missing_workshop_stubs(&game, &GameRegistry::embedded()).unwrap();
+        written.sort();
+        assert_eq!(
+            written,
+            vec![
+                data_dir.join("mod/ugc_2001.mod"),
+                data_dir.join("mod/ugc_2004.mod")
+            ]
+        );
+
+        // 2004's own descriptor has no supported_version; the stub still
+        // needs one to parse.
+        let unversioned = fs::read_to_string(&written[1]).unwrap();
+        let unversioned = mod_descriptor::parse_mod_file(&unversioned).unwrap();
+        assert_eq!(unversioned.supported_version.as_deref(), Some("*"));
+
+        let stub = fs::read_to_string(&written[0]).unwrap();
+        let descriptor = mod_descriptor::parse_mod_file(&stub).unwrap();
+        assert_eq!(descriptor.remote_file_id.as_deref(), Some("2001"));
+        assert_eq!(
+            discover_mods(&game)
+                .mods
+                .iter()
+                .find(|m| m.remote_file_id.as_deref() == Some("2001"))
+                .and_then(|m| m.descriptor_file.as_deref()),
+            Some("mod/ugc_2001.mod")
+        );
+        assert!(Path::new(descriptor.path.as_deref().unwrap()).is_absolute());
+
+        // Once written, discovery reads the stub instead of synthesizing.
+        assert!(
+            write_missing_workshop_stubs(&game, &GameRegistry::embedded())
+                .unwrap()
+                .is_empty()
+        );
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+
+    #[test]
+    fn test_discover_dlcs() {
+        let dlcs = discover_dlcs(&workshop_game("/nonexistent"));
+        let found: Vec<_> = dlcs
+            .iter()
+            .map(|d| (d.file.as_str(), d.name.as_str()))
+            .collect();
+        // dlc002 has no name and is skipped.
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "dlc/dlc001_symbols_of_domination/dlc001.dlc",
+                    "Symbols of Domination"
+                ),
+                ("dlc/dlc004_leviathans/dlc004.dlc", "Leviathans Story Pack"),
+            ]
+        );
+        assert!(!dlcs[0].affects_checksum);
+        assert!(discover_dlcs(&make_game("/nonexistent")).is_empty());
+    }
+
     #[test]
     fn test_discover_mods_ignores_non_mod_files() {
         // fixtures/discover/mod/ also contains readme.txt
         // only .mod files should be parsed
         let game = make_game("tests/fixtures/discover");
-        let mods = discover_mods(&game);
-        assert_eq!(mods.len(), 1);
+        let discovery = discover_mods(&game);
+        assert_eq!(discovery.mods.len(), 1);
+        assert_eq!(discovery.diagnostics.len(), 1);
+        assert!(
+            discovery
+                .diagnostics
+                .iter()
+                .all(|d| !d.file.ends_with("readme.txt"))
+        );
     }
 }
@@ -1,5 +1,74 @@
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
+/// Where a parse failed: enough to point a user at the offending line.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ParseLocation {
+    /// The file being parsed, when the parser was given one.
+    pub file: Option<PathBuf>,
+    /// 1-based line number.
+    pub line: usize,
+    /// 1-based column, counted in characters.
+    pub column: usize,
+    /// The offending source line, trimmed and truncated for display.
+    pub snippet: String,
+    /// What the parser was looking for at that point (e.g. "`}`").
+    pub expected: String,
+}
+
+impl ParseLocation {
+    /// Longest snippet kept, in characters.
+    const MAX_SNIPPET_CHARS: usize = 80;
+
+    /// Locate byte `offset` of `source`. Offsets past the end (or inside a
+    /// multi-byte character) are clamped to the nearest valid position.
+    pub fn at(source: &str, offset: usize, expected: impl Into<String>) -> Self {
+        let mut offset = offset.min(source.len());
+        while !source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[offset..]
+            .find('\n')
+            .map_or(source.len(), |i| offset + i);
+        Self {
+            file: None,
+            line: source[..offset].matches('\n').count() + 1,
+            column: source[line_start..offset].chars().count() + 1,
+            snippet: source[line_start..line_end]
+                .trim()
+                .chars()
+                .take(Self::MAX_SNIPPET_CHARS)
+                .collect(),
+            expected: expected.into(),
+        }
+    }
+
+    pub fn in_file(mut self, file: &Path) -> Self {
+        self.file = Some(file.to_path_buf());
+        self
+    }
+}
+
+impl fmt::Display for ParseLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file.display())?;
+        }
+        write!(
+            f,
+            "{}:{}: expected {}",
+            self.line, self.column, self.expected
+        )?;
+        if !self.snippet.is_empty() {
+            write!(f, " in `{}`", self.snippet)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum ModParseError {
     #[error("IO Error: {0}")]
@@ -9,7 +78,50 @@ pub enum ModParseError {
     MissingField(String),
 
     #[error("Mod descriptor parsing failed: {0}")]
-    ParseError(String),
+    ParseError(ParseLocation),
+
+    #[error("Invalid metadata.json: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+impl ModParseError {
+    /// Attach the descriptor's file path to a syntax error's location.
+    pub fn in_file(self, file: &Path) -> Self {
+        match self {
+            ModParseError::ParseError(location) => {
+                ModParseError::ParseError(location.in_file(file))
+            }
+            other => other,
+        }
+    }
+
+    /// The syntax error's location, if this is one.
+    pub fn location(&self) -> Option<&ParseLocation> {
+        match self {
+            ModParseError::ParseError(location) => Some(location),
+            _ => None,
+        }
+    }
+}
+
+impl From<ScriptParseError> for ModParseError {
+    fn from(err: ScriptParseError) -> Self {
+        match err {
+            ScriptParseError::ParseError(location) => ModParseError::ParseError(location),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ScriptParseError {
+    #[error("Script parsing failed: {0}")]
+    ParseError(ParseLocation),
+}
+
+#[derive(Error, Debug)]
+pub enum LocalisationParseError {
+    #[error("Localisation parsing failed: {0}")]
+    ParseError(ParseLocation),
 }
 
 #[derive(Error, Debug)]
@@ -30,12 +142,27 @@ pub enum VdfParseError {
     MissingField(String),
 
     #[error("VDF parsing failed: {0}")]
-    ParseError(String),
+    ParseError(ParseLocation),
 
     #[error("Invalid number: {0}")]
     InvalidNumber(#[from] std::num::ParseIntError),
 }
 
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum BinaryVdfError {
+    #[error("Unrecognised binary VDF header magic {0:#010x}")]
+    UnsupportedVersion(u32),
+
+    #[error("Binary VDF ends unexpectedly at byte {offset}")]
+    Truncated { offset: usize },
+
+    #[error("Unknown binary VDF value type {tag:#04x} at byte {offset}")]
+    UnknownType { tag: u8, offset: usize },
+
+    #[error("String table index {index} out of range at byte {offset}")]
+    BadStringIndex { index: u32, offset: usize },
+}
+
 #[derive(Error, Debug)]
 pub enum FileOperationError {
     #[error("Failed to save file {0}")]
@@ -49,6 +176,33 @@ pub enum FileOperationError {
 
     #[error("No installed game with app id {0}")]
     UnknownGame(u32),
+
+    #[error("Paradox launcher database: {0}")]
+    Launcher(#[from] LauncherDbError),
+
+    #[error("Not installed: {}", .0.join(", "))]
+    ModsNotInstalled(Vec<String>),
+
+    #[error("Installed but without a .mod descriptor the game can load: {}", .0.join(", "))]
+    NoDescriptor(Vec<String>),
+
+    #[error("No backup with id {0}")]
+    BackupNotFound(String),
+
+    #[error("Can't use {} as a mod folder: {reason}", .path.display())]
+    InvalidModRoot { path: PathBuf, reason: String },
+}
+
+#[derive(Error, Debug)]
+pub enum LauncherDbError {
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[error("Playset id {0} is not a UUID")]
+    InvalidId(String),
+
+    #[error("{0} is not started through the Paradox launcher, so it has no playsets")]
+    NoLauncher(String),
 }
 
 #[derive(Error, Debug)]
@@ -56,3 +210,15 @@ pub enum LaunchError {
     #[error("Could not start the game launcher: {0}")]
     Spawn(#[from] std::io::Error),
 }
+
+#[derive(Error, Debug)]
+pub enum GameRegistryError {
+    #[error("Could not read game definitions: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid game definitions TOML: {0}")]
+    Toml(#[from] toml::de::Error),
+
+    #[error("Invalid game definitions JSON: {0}")]
+    Json(#[from] serde_json::Error),
+}
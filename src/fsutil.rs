@@ -1,3 +1,4 @@
+use serde::Serialize;
 use std::fs::{self, File};
 use std::io::{self, Read, Write};
 use std::path::Path;
@@ -7,9 +8,9 @@ use std::path::Path;
 /// only exists so a pathological file can't exhaust memory.
 pub const MAX_READ_BYTES: u64 = 16 * 1024 * 1024;
 
-/// `fs::read_to_string` with a size cap, checked on the already-opened handle
+/// Read a whole file with a size cap, checked on the already-opened handle
 /// so the file can't be swapped between the check and the read.
-pub fn read_to_string_limited(path: &Path, max_bytes: u64) -> io::Result<String> {
+pub fn read_limited(path: &Path, max_bytes: u64) -> io::Result<Vec<u8>> {
     let file = File::open(path)?;
     let len = file.metadata()?.len();
     if len > max_bytes {
@@ -21,16 +22,137 @@ pub fn read_to_string_limited(path: &Path, max_bytes: u64) -> io::Result<String>
             ),
         ));
     }
-    let mut contents = String::with_capacity(len as usize);
-    file.take(max_bytes).read_to_string(&mut contents)?;
+    let mut contents = Vec::with_capacity(len as usize);
+    file.take(max_bytes).read_to_end(&mut contents)?;
     Ok(contents)
 }
 
+/// `fs::read_to_string` with a size cap (see [`read_limited`]). For files we
+/// write ourselves, which are always UTF-8; third-party text goes through
+/// [`read_text_limited`].
+pub fn read_to_string_limited(path: &Path, max_bytes: u64) -> io::Result<String> {
+    String::from_utf8(read_limited(path, max_bytes)?)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Which encoding [`decode_text`] found a file to be in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum TextEncoding {
+    Utf8,
+    /// UTF-8 with a leading byte-order mark (stripped from the text). Paradox
+    /// requires the BOM on localisation files.
+    Utf8Bom,
+    /// Not valid UTF-8; decoded as Windows-1252, which older Workshop
+    /// descriptors were often saved in.
+    Windows1252,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedText {
+    pub text: String,
+    pub encoding: TextEncoding,
+}
+
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Characters for bytes 0x80..=0x9F in Windows-1252. The five bytes it leaves
+/// undefined map to the matching C1 control, as in Latin-1, so every byte
+/// decodes to something.
+const CP1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{0081}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{008D}', '\u{017D}', '\u{008F}',
+    '\u{0090}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{009D}', '\u{017E}', '\u{0178}',
+];
+
+/// Decode third-party text: strip a UTF-8 BOM, accept valid UTF-8, and fall
+/// back to Windows-1252 (a superset of Latin-1's printable range) otherwise.
+/// Never fails, since every byte sequence is valid Windows-1252.
+pub fn decode_text(bytes: Vec<u8>) -> DecodedText {
+    let (bytes, bom) = match bytes.strip_prefix(BOM) {
+        Some(rest) => (rest.to_vec(), true),
+        None => (bytes, false),
+    };
+    match String::from_utf8(bytes) {
+        Ok(text) => DecodedText {
+            text,
+            encoding: if bom {
+                TextEncoding::Utf8Bom
+            } else {
+                TextEncoding::Utf8
+            },
+        },
+        Err(e) => DecodedText {
+            text: e
+                .as_bytes()
+                .iter()
+                .map(|&b| match b {
+                    0x80..=0x9F => CP1252_HIGH[(b - 0x80) as usize],
+                    _ => char::from(b),
+                })
+                .collect(),
+            encoding: TextEncoding::Windows1252,
+        },
+    }
+}
+
+/// The reverse of [`decode_text`]: `text` as bytes in `encoding`. `None` when
+/// the text has characters Windows-1252 can't represent.
+pub fn encode_text(text: &str, encoding: TextEncoding) -> Option<Vec<u8>> {
+    match encoding {
+        TextEncoding::Utf8 => Some(text.as_bytes().to_vec()),
+        TextEncoding::Utf8Bom => Some([BOM, text.as_bytes()].concat()),
+        TextEncoding::Windows1252 => text
+            .chars()
+            .map(|c| match u32::from(c) {
+                code @ (0..=0x7F | 0xA0..=0xFF) => Some(code as u8),
+                _ => CP1252_HIGH
+                    .iter()
+                    .position(|&high| high == c)
+                    .map(|i| 0x80 + i as u8),
+            })
+            .collect(),
+    }
+}
+
+/// Read a third-party text file (descriptor, VDF, localisation) with a size
+/// cap, detecting its encoding instead of rejecting non-UTF-8 bytes.
+pub fn read_text_limited(path: &Path, max_bytes: u64) -> io::Result<DecodedText> {
+    let decoded = decode_text(read_limited(path, max_bytes)?);
+    if decoded.encoding == TextEncoding::Windows1252 {
+        log::info!(
+            "{} is not valid UTF-8; decoded as Windows-1252",
+            path.display()
+        );
+    }
+    Ok(decoded)
+}
+
+/// Whether two files hold the same bytes. Unreadable files never match.
+pub fn same_bytes(a: &Path, b: &Path) -> bool {
+    let (Ok(mut a), Ok(mut b)) = (File::open(a), File::open(b)) else {
+        return false;
+    };
+    let mut buf_a = [0u8; 64 * 1024];
+    let mut buf_b = [0u8; 64 * 1024];
+    loop {
+        let Ok(read) = a.read(&mut buf_a) else {
+            return false;
+        };
+        if read == 0 {
+            return b.read(&mut buf_b).is_ok_and(|n| n == 0);
+        }
+        if b.read_exact(&mut buf_b[..read]).is_err() || buf_a[..read] != buf_b[..read] {
+            return false;
+        }
+    }
+}
+
 /// Write `contents` to `path` atomically: write to a temp file in the same
 /// directory, fsync, then rename over the destination. A crash mid-write can
 /// no longer leave a truncated file, and an attacker-planted symlink at `path`
 /// is replaced rather than followed.
-pub fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
     let dir = path.parent().filter(|p| !p.as_os_str().is_empty());
     let file_name = path
         .file_name()
@@ -42,7 +164,7 @@ pub fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
 
     let result = (|| {
         let mut file = File::create(&tmp)?;
-        file.write_all(contents.as_bytes())?;
+        file.write_all(contents.as_ref())?;
         file.sync_all()?;
         fs::rename(&tmp, path)
     })();
@@ -101,4 +223,41 @@ mod tests {
         assert_eq!(read_to_string_limited(&path, 10).unwrap(), "0123456789");
         let _ = fs::remove_file(&path);
     }
+
+    #[test]
+    fn test_decode_text_strips_utf8_bom() {
+        let decoded = decode_text(b"\xEF\xBB\xBFname=\"Caf\xC3\xA9\"".to_vec());
+        assert_eq!(decoded.text, "name=\"Caf\u{e9}\"");
+        assert_eq!(decoded.encoding, TextEncoding::Utf8Bom);
+    }
+
+    #[test]
+    fn test_decode_text_falls_back_to_windows_1252() {
+        // 0xE9 is Latin-1 'é'; 0x93/0x94 are Windows-1252 curly quotes.
+        let decoded = decode_text(b"name=\"Caf\xE9 \x93Deluxe\x94\"".to_vec());
+        assert_eq!(decoded.text, "name=\"Caf\u{e9} \u{201c}Deluxe\u{201d}\"");
+        assert_eq!(decoded.encoding, TextEncoding::Windows1252);
+    }
+
+    #[test]
+    fn test_encode_text_round_trips_windows_1252() {
+        let bytes = b"name=\"Caf\xE9 \x93Deluxe\x94\"".to_vec();
+        let decoded = decode_text(bytes.clone());
+        assert_eq!(encode_text(&decoded.text, decoded.encoding), Some(bytes));
+        assert_eq!(encode_text("\u{4e2d}", TextEncoding::Windows1252), None);
+        assert_eq!(
+            encode_text("a", TextEncoding::Utf8Bom),
+            Some(b"\xEF\xBB\xBFa".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_read_text_limited_reads_plain_utf8() {
+        let path = temp_path("plain.txt");
+        fs::write(&path, "plain \u{e9}").unwrap();
+        let decoded = read_text_limited(&path, MAX_READ_BYTES).unwrap();
+        assert_eq!(decoded.text, "plain \u{e9}");
+        assert_eq!(decoded.encoding, TextEncoding::Utf8);
+        let _ = fs::remove_file(&path);
+    }
 }
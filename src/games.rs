@@ -0,0 +1,272 @@
+use crate::errors::GameRegistryError;
+use crate::fsutil::{MAX_READ_BYTES, read_to_string_limited};
+use crate::models::OverrideRule;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Built-in definitions, compiled in so detection works with no config.
+const DEFAULT_GAMES: &str = include_str!("games.toml");
+
+/// How the game is normally started outside of this manager.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LauncherKind {
+    /// The Paradox Launcher v2 (Stellaris, CK3, EU4, HOI4, Imperator).
+    #[default]
+    ParadoxLauncher,
+    /// The lightweight launcher shipped with Victoria 3.
+    Dowser,
+}
+
+/// Where a game expects mod descriptors.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DescriptorStyle {
+    /// `mod/*.mod` files in the user data folder, pointing at the content.
+    #[default]
+    ModFile,
+    /// `.metadata/metadata.json` inside each mod's folder.
+    Metadata,
+}
+
+/// How the game settles duplicate objects in a script folder.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FolderMerge {
+    LastWins,
+    FirstWins,
+    /// Every definition is kept, so duplicates don't conflict (`on_actions`).
+    Merged,
+}
+
+/// A script folder whose duplicate objects aren't settled last-wins.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct FolderRule {
+    /// Mod-relative folder, e.g. `common/on_actions`. Covers its subfolders.
+    pub folder: String,
+    pub rule: FolderMerge,
+}
+
+fn default_folder_rules() -> Vec<FolderRule> {
+    vec![
+        FolderRule {
+            folder: "events".to_string(),
+            rule: FolderMerge::FirstWins,
+        },
+        FolderRule {
+            folder: "common/on_actions".to_string(),
+            rule: FolderMerge::Merged,
+        },
+    ]
+}
+
+fn default_checksum_exempt() -> Vec<String> {
+    [
+        "gfx",
+        "interface",
+        "fonts",
+        "dlc_metadata",
+        "sound",
+        "music",
+        "localisation",
+        "localization",
+    ]
+    .map(String::from)
+    .to_vec()
+}
+
+/// Everything we know about a supported game.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GameDefinition {
+    pub app_id: u32,
+    pub name: String,
+    /// Folder under `steamapps/common/` on a default install. The app
+    /// manifest's `installdir` wins when the two disagree and its folder
+    /// exists.
+    pub steam_folder: String,
+    /// Folder under the Paradox Interactive user data root.
+    pub paradox_folder: String,
+    /// Native executables relative to the install root, in priority order.
+    /// Empty means always launch through Steam.
+    #[serde(default)]
+    pub binaries: Vec<String>,
+    /// Only games started through the Paradox launcher have its playsets
+    /// synced with their collections.
+    #[serde(default)]
+    pub launcher: LauncherKind,
+    #[serde(default)]
+    pub descriptor: DescriptorStyle,
+    /// Top-level mod folders that leave the gameplay checksum untouched.
+    #[serde(default = "default_checksum_exempt")]
+    pub checksum_exempt: Vec<String>,
+    /// Script folders that don't follow the last-wins default.
+    #[serde(default = "default_folder_rules")]
+    pub folder_rules: Vec<FolderRule>,
+}
+
+impl GameDefinition {
+    /// Whether a mod-relative file path changes the gameplay checksum (and so
+    /// disables achievements and ironman).
+    pub fn affects_checksum(&self, file_path: &Path) -> bool {
+        let top = file_path
+            .components()
+            .next()
+            .and_then(|c| c.as_os_str().to_str());
+        !top.is_some_and(|top| self.checksum_exempt.iter().any(|dir| dir == top))
+    }
+
+    /// How duplicate objects in a mod-relative script folder are settled, or
+    /// `None` when the game merges them. The most specific matching rule
+    /// applies.
+    pub fn folder_rule(&self, folder: &Path) -> Option<OverrideRule> {
+        let merge = self
+            .folder_rules
+            .iter()
+            .filter(|rule| folder.starts_with(&rule.folder))
+            .max_by_key(|rule| Path::new(&rule.folder).components().count())
+            .map_or(FolderMerge::LastWins, |rule| rule.rule);
+        match merge {
+            FolderMerge::LastWins => Some(OverrideRule::LastWins),
+            FolderMerge::FirstWins => Some(OverrideRule::FirstWins),
+            FolderMerge::Merged => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GameFile {
+    #[serde(default)]
+    game: Vec<GameDefinition>,
+}
+
+/// The set of supported games: the embedded defaults, optionally overlaid with
+/// a user file.
+#[derive(Debug, Clone)]
+pub struct GameRegistry {
+    games: Vec<GameDefinition>,
+}
+
+impl GameRegistry {
+    /// Only the definitions compiled into the binary.
+    pub fn embedded() -> Self {
+        let file: GameFile = toml::from_str(DEFAULT_GAMES).expect("embedded games.toml is valid");
+        Self { games: file.game }
+    }
+
+    /// Embedded defaults plus the user's override file, if there is one. A
+    /// broken override is logged and ignored so detection keeps working.
+    pub fn load() -> Self {
+        let mut registry = Self::embedded();
+        for path in crate::locations::game_definition_candidates() {
+            if !path.exists() {
+                continue;
+            }
+            match registry.merge_file(&path) {
+                Ok(()) => log::info!("Loaded game definitions from {}", path.display()),
+                Err(e) => log::warn!("Ignoring game definitions {}: {e}", path.display()),
+            }
+        }
+        registry
+    }
+
+    /// Overlay definitions from a `.toml` or `.json` file. Entries replace any
+    /// existing definition with the same app id; new app ids are added.
+    pub fn merge_file(&mut self, path: &Path) -> Result<(), GameRegistryError> {
+        let content = read_to_string_limited(path, MAX_READ_BYTES)?;
+        let file: GameFile = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&content)?
+        } else {
+            toml::from_str(&content)?
+        };
+        for game in file.game {
+            match self.games.iter_mut().find(|g| g.app_id == game.app_id) {
+                Some(existing) => *existing = game,
+                None => self.games.push(game),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, app_id: u32) -> Option<&GameDefinition> {
+        self.games.iter().find(|g| g.app_id == app_id)
+    }
+
+    pub fn games(&self) -> &[GameDefinition] {
+        &self.games
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedded_registry_lists_supported_games() {
+        let registry = GameRegistry::embedded();
+        let stellaris = registry.get(281990).unwrap();
+        assert_eq!(stellaris.binaries, vec!["stellaris"]);
+        assert_eq!(stellaris.launcher, LauncherKind::ParadoxLauncher);
+        assert_eq!(
+            registry.get(529340).unwrap().descriptor,
+            DescriptorStyle::Metadata
+        );
+        let sti = registry
+            .games()
+            .iter()
+            .find(|g| g.name == "Star Trek: Infinite")
+            .unwrap();
+        assert!(sti.binaries.is_empty());
+    }
+
+    #[test]
+    fn test_merge_file_overrides_and_adds_games() {
+        let mut registry = GameRegistry::embedded();
+        let count = registry.games().len();
+        registry
+            .merge_file(Path::new("tests/fixtures/games/override.toml"))
+            .unwrap();
+        assert_eq!(registry.games().len(), count + 1);
+        assert_eq!(
+            registry.get(281990).unwrap().paradox_folder,
+            "Stellaris Beta"
+        );
+        assert_eq!(registry.get(42).unwrap().name, "Test Game");
+
+        registry
+            .merge_file(Path::new("tests/fixtures/games/override.json"))
+            .unwrap();
+        assert_eq!(registry.get(42).unwrap().binaries, vec!["bin/test"]);
+    }
+
+    #[test]
+    fn test_checksum_exempt_folders() {
+        let registry = GameRegistry::embedded();
+        let stellaris = registry.get(281990).unwrap();
+        assert!(!stellaris.affects_checksum(Path::new("gfx/models/ship.mesh")));
+        assert!(!stellaris.affects_checksum(Path::new("localisation/english/l.yml")));
+        assert!(stellaris.affects_checksum(Path::new("common/defines/00.txt")));
+    }
+
+    #[test]
+    fn test_folder_rules_pick_the_most_specific_folder() {
+        let mut game = GameRegistry::embedded().get(281990).unwrap().clone();
+        assert_eq!(
+            game.folder_rule(Path::new("common/traits")),
+            Some(OverrideRule::LastWins)
+        );
+        assert_eq!(
+            game.folder_rule(Path::new("events/sub")),
+            Some(OverrideRule::FirstWins)
+        );
+        assert_eq!(game.folder_rule(Path::new("common/on_actions")), None);
+
+        game.folder_rules.push(FolderRule {
+            folder: "events/sub".to_string(),
+            rule: FolderMerge::LastWins,
+        });
+        assert_eq!(
+            game.folder_rule(Path::new("events/sub")),
+            Some(OverrideRule::LastWins)
+        );
+    }
+}
@@ -2,7 +2,14 @@ use std::path::{Path, PathBuf};
 use std::process::Command;
 
 use crate::errors::LaunchError;
+use crate::fsutil::read_limited;
+use crate::games::GameRegistry;
 use crate::models::DetectedGame;
+use crate::parser::binary_vdf::{BinaryValue, find_appinfo};
+
+/// `appinfo.vdf` holds every app the client has seen and runs to a couple of
+/// hundred megabytes on large libraries. Only the game's own entry is parsed.
+const MAX_APPINFO_BYTES: u64 = 512 * 1024 * 1024;
 
 /// Launch a detected game.
 ///
@@ -15,7 +22,7 @@ use crate::models::DetectedGame;
 ///
 /// The child is spawned detached; we do not wait for the game to exit.
 pub fn launch_game(game: &DetectedGame) -> Result<(), LaunchError> {
-    if let Some(binary) = resolve_game_binary(game) {
+    if let Some(binary) = resolve_game_binary(game, &GameRegistry::load()) {
         let mut command = Command::new(&binary);
         // Paradox binaries expect their working directory to be the install
         // root (they resolve game data relative to it), even when the
@@ -28,30 +35,69 @@ pub fn launch_game(game: &DetectedGame) -> Result<(), LaunchError> {
     launch_via_steam(game.app_id)
 }
 
-/// Relative paths (within the game's Steam install dir) to the real game
-/// executable on Linux, in priority order. Paradox ships the binary either at
-/// the install root or under `binaries/`, alongside the launcher we skip.
-fn game_binary_candidates(app_id: u32) -> &'static [&'static str] {
-    match app_id {
-        281990 => &["stellaris"],                       // Stellaris
-        236850 => &["eu4"],                             // Europa Universalis IV
-        394360 => &["hoi4"],                            // Hearts of Iron IV
-        1158310 => &["binaries/ck3", "ck3"],            // Crusader Kings III
-        529340 => &["binaries/victoria3", "victoria3"], // Victoria 3
-        859580 => &["binaries/imperator", "imperator"], // Imperator: Rome
-        _ => &[],
-    }
-}
-
-/// First existing executable file among the game's known binary candidates.
-fn resolve_game_binary(game: &DetectedGame) -> Option<PathBuf> {
+/// First existing executable file among the game's known binaries, which are
+/// relative to the Steam install dir in priority order. Paradox ships the
+/// binary either at the install root or under `binaries/`, alongside the
+/// launcher we skip. If an update moved it, the executables Steam's own launch
+/// options name for this platform are tried next.
+fn resolve_game_binary(game: &DetectedGame, registry: &GameRegistry) -> Option<PathBuf> {
     let install = Path::new(&game.install_path);
-    game_binary_candidates(game.app_id)
+    let binaries = &registry.get(game.app_id)?.binaries;
+    if binaries.is_empty() {
+        return None;
+    }
+    binaries
         .iter()
+        .cloned()
+        .chain(std::iter::once_with(|| steam_launch_executables(game)).flatten())
         .map(|rel| install.join(rel))
         .find(|candidate| candidate.is_file())
 }
 
+/// The `config/launch` executables in Steam's `appcache/appinfo.vdf` for this
+/// platform, in Steam's order. Empty when the game wasn't found through a
+/// Steam install or the cache can't be read.
+fn steam_launch_executables(game: &DetectedGame) -> Vec<String> {
+    // `source` is `<steam root>/steamapps/libraryfolders.vdf`.
+    let Some(steam_root) = game
+        .source
+        .as_ref()
+        .and_then(|source| source.path.parent()?.parent())
+    else {
+        return Vec::new();
+    };
+    let path = steam_root.join("appcache/appinfo.vdf");
+    let app = match read_limited(&path, MAX_APPINFO_BYTES) {
+        Ok(bytes) => find_appinfo(&bytes, game.app_id).map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    let app = match app {
+        Ok(app) => app,
+        Err(e) => {
+            log::info!("Not using {}: {e}", path.display());
+            return Vec::new();
+        }
+    };
+    let Some(launch) = app
+        .as_ref()
+        .and_then(|app| app.root()?.get_path(&["config", "launch"])?.as_map())
+    else {
+        return Vec::new();
+    };
+    launch
+        .iter()
+        .map(|entry| &entry.value)
+        .filter(|option| {
+            option
+                .get_path(&["config", "oslist"])
+                .and_then(BinaryValue::as_str)
+                .is_none_or(|oslist| oslist.split(',').any(|os| os == std::env::consts::OS))
+        })
+        .filter_map(|option| option.get("executable")?.as_str())
+        .map(|executable| executable.replace('\\', "/"))
+        .collect()
+}
+
 /// Launch through the platform's URL opener, which Steam registers as the
 /// handler for `steam:` URLs on install (works whether Steam is running or not
 /// and without hardcoding the Steam executable path):
@@ -90,27 +136,26 @@ fn launch_via_steam(app_id: u32) -> Result<(), LaunchError> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::{LocationSource, SourceKind};
 
     fn game(app_id: u32, install_path: &str) -> DetectedGame {
         DetectedGame {
-            app_id,
             install_path: install_path.to_string(),
-            game_name: "Test".to_string(),
-            paradox_data_path: String::new(),
+            ..DetectedGame::for_test(app_id, "")
         }
     }
 
     #[test]
     fn resolves_binary_at_install_root() {
         let install = "tests/fixtures/launch/stellaris_install";
-        let resolved = resolve_game_binary(&game(281990, install));
+        let resolved = resolve_game_binary(&game(281990, install), &GameRegistry::embedded());
         assert_eq!(resolved, Some(Path::new(install).join("stellaris")));
     }
 
     #[test]
     fn resolves_binary_under_binaries_subdir() {
         let install = "tests/fixtures/launch/ck3_install";
-        let resolved = resolve_game_binary(&game(1158310, install));
+        let resolved = resolve_game_binary(&game(1158310, install), &GameRegistry::embedded());
         assert_eq!(
             resolved,
             Some(Path::new(install).join("binaries").join("ck3"))
@@ -119,15 +164,61 @@ mod tests {
 
     #[test]
     fn returns_none_when_binary_missing() {
-        let resolved = resolve_game_binary(&game(281990, "tests/fixtures/launch/empty_install"));
+        let resolved = resolve_game_binary(
+            &game(281990, "tests/fixtures/launch/empty_install"),
+            &GameRegistry::embedded(),
+        );
         assert_eq!(resolved, None);
     }
 
+    fn game_from_steam(app_id: u32, install_path: &str) -> DetectedGame {
+        DetectedGame {
+            source: Some(LocationSource {
+                kind: SourceKind::Default,
+                path: PathBuf::from("tests/fixtures/launch/steam/steamapps/libraryfolders.vdf"),
+            }),
+            ..game(app_id, install_path)
+        }
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn reads_launch_executables_from_appinfo() {
+        let ck3 = game_from_steam(1158310, "tests/fixtures/launch/ck3_install");
+        assert_eq!(steam_launch_executables(&ck3), vec!["binaries/ck3"]);
+        assert!(steam_launch_executables(&game_from_steam(999999, "")).is_empty());
+        assert!(steam_launch_executables(&game(1158310, "")).is_empty());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn falls_back_to_appinfo_when_known_binaries_are_missing() {
+        let path =
+            std::env::temp_dir().join(format!("ferrous_launch_games_{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "[[game]]\napp_id = 281990\nname = \"Stellaris\"\nsteam_folder = \"Stellaris\"\nparadox_folder = \"Stellaris\"\nbinaries = [\"stellaris_renamed\"]\n",
+        )
+        .unwrap();
+        let mut registry = GameRegistry::embedded();
+        registry.merge_file(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        let install = "tests/fixtures/launch/stellaris_install";
+        assert_eq!(
+            resolve_game_binary(&game_from_steam(281990, install), &registry),
+            Some(Path::new(install).join("stellaris"))
+        );
+        assert_eq!(resolve_game_binary(&game(281990, install), &registry), None);
+    }
+
     #[test]
     fn returns_none_for_unknown_game() {
         // The Stellaris binary exists here, but the app id isn't a known game.
-        let resolved =
-            resolve_game_binary(&game(999999, "tests/fixtures/launch/stellaris_install"));
+        let resolved = resolve_game_binary(
+            &game(999999, "tests/fixtures/launch/stellaris_install"),
+            &GameRegistry::embedded(),
+        );
         assert_eq!(resolved, None);
     }
 }
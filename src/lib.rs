@@ -1,11 +1,15 @@
 pub mod achievements;
+pub mod backups;
 pub mod collections;
 pub mod conflict;
 pub mod dependency;
 pub mod detector;
 pub mod errors;
 pub mod fsutil;
+pub mod games;
 pub mod launch;
 pub mod locations;
 pub mod models;
 pub mod parser;
+pub mod playsets;
+pub mod settings;
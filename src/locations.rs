@@ -1,16 +1,57 @@
+use crate::errors::DetectionError;
+use crate::models::{LibraryVdf, LocationSource, SourceKind};
+use crate::settings::Settings;
 use std::path::{Path, PathBuf};
 
+/// Where unit tests keep the manager's own files instead: they save
+/// collections and take backups, and must not read the user's settings or
+/// game definitions.
+fn test_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("ferrous_test_data_{}", std::process::id()))
+}
+
 pub fn data_dir() -> PathBuf {
+    if cfg!(test) {
+        return test_dir().join("mod-collections");
+    }
     dirs::data_local_dir()
         .unwrap_or_else(|| PathBuf::from("~/.local/share"))
         .join("ferrous-mod-manager")
         .join("mod-collections")
 }
 
+/// Per-user configuration for the manager itself, e.g. `~/.config/ferrous-mod-manager`.
+pub fn config_dir() -> PathBuf {
+    if cfg!(test) {
+        return test_dir().join("config");
+    }
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("~/.config"))
+        .join("ferrous-mod-manager")
+}
+
+/// The manager's `Settings` file.
+pub fn settings_path() -> PathBuf {
+    config_dir().join("settings.json")
+}
+
+/// User game definition overrides, in load order. Both may exist; later files
+/// win.
+pub fn game_definition_candidates() -> Vec<PathBuf> {
+    let dir = config_dir();
+    vec![dir.join("games.toml"), dir.join("games.json")]
+}
+
 pub fn game_data_dir(app_id: u32) -> PathBuf {
     data_dir().join(app_id.to_string())
 }
 
+/// Snapshots of game files taken before the manager overwrites them. Kept out
+/// of `game_data_dir`, which only holds collections.
+pub fn backup_dir(app_id: u32) -> PathBuf {
+    data_dir().join("backups").join(app_id.to_string())
+}
+
 /// Root directory holding Paradox Interactive's per-game data, derived from the
 /// user's home directory so it stays testable against fixture homes.
 ///
@@ -29,10 +70,10 @@ pub fn paradox_data_root(home: &Path) -> PathBuf {
     }
 }
 
-/// Candidate locations for Steam's `libraryfolders.vdf`, in priority order. The
-/// caller uses the first one that exists on disk.
+/// Default locations for Steam's `libraryfolders.vdf`, in priority order. See
+/// [`steam_library_vdf_sources`] for the ones actually read.
 ///
-/// On Linux these are home-relative (native, flatpak, and the older
+/// On Linux these are home-relative (native, flatpak, snap, and the older
 /// `~/.local/share/Steam` layout). On Windows Steam is rarely under the user
 /// home, so the standard `Program Files` install locations are used instead.
 #[cfg_attr(windows, allow(unused_variables))]
@@ -58,70 +99,165 @@ pub fn steam_library_vdf_candidates(home: &Path) -> Vec<PathBuf> {
                     .join("share")
                     .join("Steam"),
             ),
+            steamapps_vdf(
+                home.join("snap")
+                    .join("steam")
+                    .join("common")
+                    .join(".local")
+                    .join("share")
+                    .join("Steam"),
+            ),
         ]
     }
 }
 
+/// Keep the sources that exist, each once. The same folder is often reachable
+/// by two paths (`~/.steam/steam` is usually a symlink to
+/// `~/.local/share/Steam`), so duplicates are found by canonical path; the
+/// path as listed is kept.
+fn existing_unique(sources: impl IntoIterator<Item = LocationSource>) -> Vec<LocationSource> {
+    let mut seen = Vec::new();
+    let mut unique = Vec::new();
+    for source in sources {
+        let Ok(canonical) = source.path.canonicalize() else {
+            continue;
+        };
+        if !seen.contains(&canonical) {
+            seen.push(canonical);
+            unique.push(source);
+        }
+    }
+    unique
+}
+
+/// Every `libraryfolders.vdf` to read: the default candidates that exist,
+/// then one per configured Steam root.
+pub fn steam_library_vdf_sources(home: &Path, settings: &Settings) -> Vec<LocationSource> {
+    let defaults = steam_library_vdf_candidates(home)
+        .into_iter()
+        .map(|path| LocationSource {
+            kind: SourceKind::Default,
+            path,
+        });
+    let configured = settings.steam_roots.iter().map(|root| LocationSource {
+        kind: SourceKind::Settings,
+        path: if root.is_file() {
+            root.clone()
+        } else {
+            root.join("steamapps").join("libraryfolders.vdf")
+        },
+    });
+    existing_unique(defaults.chain(configured))
+}
+
+/// Paradox data roots to look for game folders in: configured ones that
+/// exist first, so the user's choice wins when several hold a game's folder,
+/// then the default.
+pub fn paradox_data_roots(home: &Path, settings: &Settings) -> Vec<LocationSource> {
+    let mut roots = existing_unique(settings.paradox_roots.iter().map(|path| LocationSource {
+        kind: SourceKind::Settings,
+        path: path.clone(),
+    }));
+    roots.push(LocationSource {
+        kind: SourceKind::Default,
+        path: paradox_data_root(home),
+    });
+    roots
+}
+
+/// The Steam libraries listed in one `libraryfolders.vdf`.
+pub fn read_library_folders(vdf_path: &Path) -> Result<Vec<LibraryVdf>, DetectionError> {
+    let content = crate::fsutil::read_text_limited(vdf_path, crate::fsutil::MAX_READ_BYTES)?;
+    Ok(crate::parser::vdf::parse_vdf_file(&content.text)?)
+}
+
 /// The canonicalized directory roots that mod content may legitimately live
 /// under. Descriptor `path=` values come from third-party `.mod` files
 /// (Steam Workshop content), so they are untrusted: before walking one we
 /// require it to resolve inside these roots, otherwise a malicious descriptor
 /// (`path="/"`) could send `WalkDir` across the whole filesystem.
 #[derive(Debug, Clone)]
-pub struct ModRoots(Vec<PathBuf>);
+pub struct ModRoots(Vec<LocationSource>);
 
 impl ModRoots {
-    /// Roots for the current user: the Paradox data dir (local mods) plus
-    /// every Steam library's `steamapps/workshop/content` dir (workshop mods).
+    /// Roots for the current user and their saved settings.
     pub fn detect() -> Self {
         match dirs::home_dir() {
-            Some(home) => Self::for_home(&home),
+            Some(home) => Self::for_home(&home, &Settings::load()),
             None => Self(Vec::new()),
         }
     }
 
-    pub fn for_home(home: &Path) -> Self {
-        let mut roots = vec![paradox_data_root(home)];
-        if let Some(vdf_path) = steam_library_vdf_candidates(home)
-            .into_iter()
-            .find(|p| p.exists())
-        {
-            match crate::fsutil::read_to_string_limited(&vdf_path, crate::fsutil::MAX_READ_BYTES)
-                .map_err(|e| e.to_string())
-                .and_then(|c| crate::parser::vdf::parse_vdf_file(&c).map_err(|e| e.to_string()))
-            {
+    /// Every Paradox data root (local mods), every Steam library's
+    /// `steamapps/workshop/content` dir (workshop mods) across all
+    /// `libraryfolders.vdf` sources, and the configured extra mod roots.
+    pub fn for_home(home: &Path, settings: &Settings) -> Self {
+        let mut roots = paradox_data_roots(home, settings);
+        for vdf in steam_library_vdf_sources(home, settings) {
+            match read_library_folders(&vdf.path) {
                 Ok(libraries) => {
                     for library in libraries {
-                        roots.push(
-                            Path::new(&library.path)
+                        roots.push(LocationSource {
+                            kind: vdf.kind,
+                            path: Path::new(&library.path)
                                 .join("steamapps")
                                 .join("workshop")
                                 .join("content"),
-                        );
+                        });
                     }
                 }
-                Err(e) => log::warn!("Could not read Steam libraries for mod roots: {e}"),
+                Err(e) => log::warn!(
+                    "Could not read Steam libraries for mod roots from {}: {e}",
+                    vdf.path.display()
+                ),
             }
         }
-        Self::from_roots(roots)
+        roots.extend(
+            settings
+                .safe_mod_roots(home)
+                .into_iter()
+                .map(|path| LocationSource {
+                    kind: SourceKind::Settings,
+                    path,
+                }),
+        );
+        Self::from_sources(roots)
     }
 
     /// Build from explicit roots (used by tests and tools that already know
     /// where mods live). Roots that don't exist are dropped.
     pub fn from_roots(roots: impl IntoIterator<Item = PathBuf>) -> Self {
+        Self::from_sources(roots.into_iter().map(|path| LocationSource {
+            kind: SourceKind::Default,
+            path,
+        }))
+    }
+
+    fn from_sources(roots: impl IntoIterator<Item = LocationSource>) -> Self {
         Self(
             roots
                 .into_iter()
-                .filter_map(|r| r.canonicalize().ok())
+                .filter_map(|r| {
+                    Some(LocationSource {
+                        path: r.path.canonicalize().ok()?,
+                        kind: r.kind,
+                    })
+                })
                 .collect(),
         )
     }
 
+    /// The allowed root a mod path resolves under, if any.
+    pub fn source_of(&self, path: &str) -> Option<&LocationSource> {
+        let canonical = Path::new(path).canonicalize().ok()?;
+        self.0.iter().find(|root| canonical.starts_with(&root.path))
+    }
+
     /// Canonicalize an untrusted mod path (resolving symlinks and `..`) and
     /// return it only if it lies under one of the allowed roots.
     pub fn checked_path(&self, path: &str) -> Option<PathBuf> {
         let canonical = Path::new(path).canonicalize().ok()?;
-        if self.0.iter().any(|root| canonical.starts_with(root)) {
+        if self.0.iter().any(|root| canonical.starts_with(&root.path)) {
             Some(canonical)
         } else {
             log::warn!("Refusing to scan mod path outside known mod directories: {path}");
@@ -173,6 +309,41 @@ mod tests {
         let _ = std::fs::remove_dir_all(&base);
     }
 
+    #[test]
+    fn test_mod_roots_report_their_source() {
+        let base = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+        let settings = Settings {
+            mod_roots: vec![base.join("conflict")],
+            ..Default::default()
+        };
+        let roots = ModRoots::for_home(&base.join("fake_home"), &settings);
+
+        let extra = base.join("conflict/mod_a").to_string_lossy().into_owned();
+        assert_eq!(roots.source_of(&extra).unwrap().kind, SourceKind::Settings);
+        let workshop = base
+            .join("fake_home/library/steamapps/workshop/content/281990/1001")
+            .to_string_lossy()
+            .into_owned();
+        assert_eq!(
+            roots.source_of(&workshop).unwrap().kind,
+            SourceKind::Default
+        );
+        assert!(roots.source_of("/etc").is_none());
+    }
+
+    #[test]
+    fn test_unsafe_mod_roots_from_settings_are_dropped() {
+        let base = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+        let home = base.join("fake_home");
+        let settings = Settings {
+            mod_roots: vec![PathBuf::from("/"), home.clone()],
+            ..Default::default()
+        };
+        let roots = ModRoots::for_home(&home, &settings);
+        assert!(roots.source_of("/etc").is_none());
+        assert!(roots.0.iter().all(|root| root.kind != SourceKind::Settings));
+    }
+
     #[test]
     fn test_paradox_data_root_linux_layout() {
         let root = paradox_data_root(Path::new("/home/user"));
@@ -1 +1,540 @@
-fn main() {}
+use std::error::Error;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+
+use ferrous_mod_manager::collections;
+use ferrous_mod_manager::games::GameRegistry;
+use ferrous_mod_manager::locations::ModRoots;
+use ferrous_mod_manager::models::{DetectedGame, ModCollection, ModDescriptor, ModEntry};
+use ferrous_mod_manager::{achievements, conflict, dependency, detector, launch};
+
+type CliResult<T = ()> = Result<T, Box<dyn Error>>;
+
+/// Manage Paradox game mods and collections without the GUI.
+#[derive(Parser)]
+#[command(name = "ferrous-mod-manager", version)]
+struct Cli {
+    /// Print machine-readable JSON instead of text.
+    #[arg(long, global = true)]
+    json: bool,
+    /// Use this directory as the home folder (Steam, Paradox data and the
+    /// collection store are all resolved under it), e.g. a home built by the
+    /// `gen_mock_home` example. Not supported on Windows.
+    #[arg(long, global = true, value_name = "DIR")]
+    home: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List installed, supported games.
+    Games,
+    /// List a game's installed mods and any descriptors that failed to parse.
+    Mods { game: String },
+    /// Manage a game's mod collections.
+    #[command(subcommand)]
+    Collections(CollectionsCommand),
+    /// File conflicts between mods (a collection's enabled mods, or all).
+    Conflicts {
+        game: String,
+        #[arg(long)]
+        collection: Option<String>,
+    },
+    /// Objects (traits, events, defines, ...) defined by more than one mod.
+    Objects {
+        game: String,
+        #[arg(long)]
+        collection: Option<String>,
+    },
+    /// Localisation keys defined by more than one mod, and each mod's
+    /// translation coverage.
+    Localisation {
+        game: String,
+        #[arg(long)]
+        collection: Option<String>,
+    },
+    /// Which mods change the checksum and so disable achievements.
+    Achievements {
+        game: String,
+        #[arg(long)]
+        collection: Option<String>,
+    },
+    /// A mod's dependency load chain and any missing dependencies.
+    Deps { game: String, mod_id: String },
+    /// Start the game.
+    Launch { game: String },
+}
+
+#[derive(Subcommand)]
+enum CollectionsCommand {
+    List {
+        game: String,
+    },
+    Create {
+        game: String,
+        name: String,
+    },
+    /// Write a collection to the game's dlc_load.json (and launcher playset).
+    Apply {
+        game: String,
+        collection: String,
+    },
+    /// Import from an exported collection file, the launcher's playsets, or
+    /// the game's current dlc_load.json.
+    Import {
+        game: String,
+        file: Option<PathBuf>,
+        #[arg(long, conflicts_with = "file")]
+        launcher: bool,
+        /// Name for the collection created from dlc_load.json.
+        #[arg(long, value_name = "NAME", conflicts_with_all = ["file", "launcher"])]
+        dlc_load: Option<String>,
+    },
+    Export {
+        game: String,
+        collection: String,
+        file: PathBuf,
+    },
+}
+
+/// Accepts a Steam app id or a game name (case-insensitive).
+fn find_game(query: &str) -> CliResult<DetectedGame> {
+    let games = detector::detect_games()?;
+    games
+        .into_iter()
+        .find(|g| g.app_id.to_string() == query || g.game_name.eq_ignore_ascii_case(query))
+        .ok_or_else(|| format!("No installed game matches '{query}'").into())
+}
+
+/// Accepts a collection id or name (case-insensitive).
+fn find_collection(game: &DetectedGame, query: &str) -> CliResult<ModCollection> {
+    collections::load_or_create_collections_for_game(game.app_id)?
+        .into_iter()
+        .find(|c| c.id.to_string() == query || c.name.eq_ignore_ascii_case(query))
+        .ok_or_else(|| format!("{} has no collection '{query}'", game.game_name).into())
+}
+
+/// The entries a command should look at, with the installed mods they refer
+/// to: the collection's entries, or every installed mod enabled in discovery
+/// order.
+fn selected_entries(
+    game: &DetectedGame,
+    collection: Option<&str>,
+) -> CliResult<(Vec<ModEntry>, Vec<ModDescriptor>)> {
+    let installed = detector::discover_mods(game).mods;
+    let entries = match collection {
+        Some(query) => find_collection(game, query)?.mods,
+        None => installed
+            .iter()
+            .map(|m| ModEntry {
+                mod_id: m.mod_id().to_string(),
+                enabled: true,
+            })
+            .collect(),
+    };
+    Ok((entries, installed))
+}
+
+/// The installed mods `selected_entries` enables, in load order.
+fn selected_mods(game: &DetectedGame, collection: Option<&str>) -> CliResult<Vec<ModDescriptor>> {
+    let (entries, installed) = selected_entries(game, collection)?;
+    Ok(entries
+        .iter()
+        .filter(|entry| entry.enabled)
+        .filter_map(|entry| installed.iter().find(|m| m.mod_id() == entry.mod_id))
+        .cloned()
+        .collect())
+}
+
+fn mod_label(mods: &[ModDescriptor], mod_id: &str) -> String {
+    mods.iter()
+        .find(|m| m.mod_id() == mod_id)
+        .and_then(|m| m.name.clone())
+        .unwrap_or_else(|| mod_id.to_string())
+}
+
+fn print_json(value: &impl Serialize) -> CliResult {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+fn print_collection(collection: &ModCollection) {
+    println!("{}  {}", collection.id, collection.name);
+    for entry in &collection.mods {
+        let mark = if entry.enabled { "x" } else { " " };
+        println!("  [{mark}] {}", entry.mod_id);
+    }
+}
+
+fn run(cli: Cli) -> CliResult {
+    if let Some(home) = &cli.home {
+        // Safe: this runs before anything else is started. Dropping the XDG
+        // overrides makes every per-user path fall back to the new home.
+        unsafe {
+            std::env::set_var("HOME", home);
+            std::env::remove_var("XDG_DATA_HOME");
+            std::env::remove_var("XDG_CONFIG_HOME");
+        }
+    }
+    let json = cli.json;
+    match cli.command {
+        Command::Games => {
+            let games = detector::detect_games()?;
+            if json {
+                return print_json(&games);
+            }
+            for game in games {
+                println!(
+                    "{:>8}  {}  ({})",
+                    game.app_id, game.game_name, game.install_path
+                );
+            }
+        }
+        Command::Mods { game } => {
+            let discovery = detector::discover_mods(&find_game(&game)?);
+            if json {
+                return print_json(&discovery);
+            }
+            for m in &discovery.mods {
+                println!(
+                    "{}  {}",
+                    m.mod_id(),
+                    m.name.as_deref().unwrap_or("(unnamed)")
+                );
+            }
+            for d in &discovery.diagnostics {
+                eprintln!("warning: {}: {}", d.file.display(), d.message);
+            }
+            if let Some(e) = &discovery.workshop_error {
+                eprintln!("warning: workshop state not read: {e}");
+            }
+        }
+        Command::Collections(command) => run_collections(command, json)?,
+        Command::Conflicts { game, collection } => {
+            let game = find_game(&game)?;
+            let (entries, installed) = selected_entries(&game, collection.as_deref())?;
+            let report = conflict::conflict_detection(
+                &entries,
+                &installed,
+                Some(&conflict::vanilla_dir(&game)),
+                &ModRoots::detect(),
+            );
+            if json {
+                return print_json(&report);
+            }
+            for c in &report.conflicts {
+                println!(
+                    "{} [{:?}, {:?}]: {} wins over {}",
+                    c.file_path.display(),
+                    c.category,
+                    c.kind,
+                    c.winner,
+                    c.overridden.join(", ")
+                );
+            }
+            for o in &report.vanilla_overrides {
+                let note = if o.wiped {
+                    " (deleted by replace_path)"
+                } else if o.vanilla_newer {
+                    " (game file updated since)"
+                } else {
+                    ""
+                };
+                println!(
+                    "{}: {} overrides the game's copy{note}",
+                    o.file_path.display(),
+                    o.mod_name
+                );
+            }
+        }
+        Command::Objects { game, collection } => {
+            let game = find_game(&game)?;
+            let (entries, installed) = selected_entries(&game, collection.as_deref())?;
+            let registry = GameRegistry::load();
+            let definition = registry
+                .get(game.app_id)
+                .ok_or_else(|| format!("No game definition for {}", game.game_name))?;
+            let conflicts = conflict::objects::object_conflicts(
+                &entries,
+                &installed,
+                definition,
+                &ModRoots::detect(),
+            );
+            if json {
+                return print_json(&conflicts);
+            }
+            for c in &conflicts {
+                println!(
+                    "{} in {} [{:?}]: {} wins over {}",
+                    c.key,
+                    c.folder.display(),
+                    c.rule,
+                    c.winner,
+                    c.overridden.join(", ")
+                );
+            }
+        }
+        Command::Localisation { game, collection } => {
+            let game = find_game(&game)?;
+            let (entries, installed) = selected_entries(&game, collection.as_deref())?;
+            let report = conflict::localisation::localisation_report(
+                &entries,
+                &installed,
+                &ModRoots::detect(),
+            );
+            if json {
+                return print_json(&report);
+            }
+            for c in &report.conflicts {
+                println!(
+                    "{} [{}]: {} wins with \"{}\"",
+                    c.key, c.language, c.winner, c.winning_value
+                );
+            }
+            for m in &report.coverage {
+                for language in m.languages.iter().filter(|l| !l.missing.is_empty()) {
+                    println!(
+                        "{}: {} of {} english keys missing in {}",
+                        m.mod_name,
+                        language.missing.len(),
+                        m.english_keys,
+                        language.language
+                    );
+                }
+            }
+        }
+        Command::Achievements { game, collection } => {
+            let game = find_game(&game)?;
+            let mods = selected_mods(&game, collection.as_deref())?;
+            let registry = GameRegistry::load();
+            let definition = registry
+                .get(game.app_id)
+                .ok_or_else(|| format!("No game definition for {}", game.game_name))?;
+            let statuses =
+                achievements::achievement_status_for_mods(&mods, definition, &ModRoots::detect());
+            if json {
+                return print_json(&statuses);
+            }
+            for s in &statuses {
+                let verdict = if s.compatible {
+                    "compatible".to_string()
+                } else {
+                    format!("disables achievements ({:?})", s.gameplay_categories)
+                };
+                println!("{}: {verdict}", mod_label(&mods, &s.mod_id));
+            }
+        }
+        Command::Deps { game, mod_id } => {
+            let mods = detector::discover_mods(&find_game(&game)?).mods;
+            let resolution = dependency::resolve_load_chain(&mod_id, &mods);
+            if json {
+                return print_json(&resolution);
+            }
+            for id in &resolution.chain {
+                println!("{}", mod_label(&mods, id));
+            }
+            for missing in &resolution.missing {
+                println!(
+                    "missing: {} (required by {})",
+                    missing.name, missing.required_by
+                );
+            }
+        }
+        Command::Launch { game } => launch::launch_game(&find_game(&game)?)?,
+    }
+    Ok(())
+}
+
+fn run_collections(command: CollectionsCommand, json: bool) -> CliResult {
+    match command {
+        CollectionsCommand::List { game } => {
+            let game = find_game(&game)?;
+            let collections = collections::load_or_create_collections_for_game(game.app_id)?;
+            if json {
+                return print_json(&collections);
+            }
+            collections.iter().for_each(print_collection);
+        }
+        CollectionsCommand::Create { game, name } => {
+            let game = find_game(&game)?;
+            let collection = collections::create_collection_for_game(game.app_id, name)?;
+            if json {
+                return print_json(&collection);
+            }
+            print_collection(&collection);
+        }
+        CollectionsCommand::Apply { game, collection } => {
+            let game = find_game(&game)?;
+            let collection = find_collection(&game, &collection)?;
+            let unregistered = collections::apply_mod_collection_for_game(&game, &collection)?;
+            if json {
+                return print_json(&serde_json::json!({
+                    "collection": collection,
+                    "unregistered": unregistered,
+                }));
+            }
+            println!("Applied '{}' to {}", collection.name, game.game_name);
+            if !unregistered.is_empty() {
+                eprintln!(
+                    "warning: the Paradox launcher doesn't know {} mod(s), left out of its playset: {}",
+                    unregistered.len(),
+                    unregistered.join(", ")
+                );
+            }
+        }
+        CollectionsCommand::Import {
+            game,
+            file,
+            launcher,
+            dlc_load,
+        } => {
+            let game = find_game(&game)?;
+            let imported = if let Some(file) = file {
+                vec![collections::import_collection_for_game(game.app_id, &file)?]
+            } else if launcher {
+                collections::import_launcher_playsets_for_game(&game)?
+            } else if let Some(name) = dlc_load {
+                let imported = collections::collection_from_dlc_load(&game, &name)?;
+                collections::save_collection_for_game(game.app_id, &imported.collection)?;
+                if json {
+                    return print_json(&imported);
+                }
+                for entry in &imported.unresolved {
+                    eprintln!("warning: {entry} matches no installed mod");
+                }
+                vec![imported.collection]
+            } else {
+                return Err("Give a FILE, --launcher or --dlc-load NAME to import from".into());
+            };
+            if json {
+                return print_json(&imported);
+            }
+            imported.iter().for_each(print_collection);
+        }
+        CollectionsCommand::Export {
+            game,
+            collection,
+            file,
+        } => {
+            let game = find_game(&game)?;
+            let collection = find_collection(&game, &collection)?;
+            collection.save(&file)?;
+            if json {
+                return print_json(&serde_json::json!({ "file": file, "id": collection.id }));
+            }
+            println!("Exported '{}' to {}", collection.name, file.display());
+        }
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ferrous_mod_manager::models::DlcLoad;
+    use std::path::Path;
+
+    fn run_args(args: &[&str]) -> CliResult {
+        run(Cli::try_parse_from(
+            ["ferrous-mod-manager"].iter().chain(args),
+        )?)
+    }
+
+    /// Copy `fake_home`'s Steam folder into a scratch home. Its libraries are
+    /// listed relative to the repo root, so games and workshop items still
+    /// come from the fixture while everything written lands in the copy.
+    fn scratch_home() -> PathBuf {
+        fn copy_dir(from: &Path, to: &Path) {
+            std::fs::create_dir_all(to).unwrap();
+            for entry in std::fs::read_dir(from).unwrap() {
+                let entry = entry.unwrap();
+                if entry.file_type().unwrap().is_dir() {
+                    copy_dir(&entry.path(), &to.join(entry.file_name()));
+                } else {
+                    std::fs::copy(entry.path(), to.join(entry.file_name())).unwrap();
+                }
+            }
+        }
+        let home = std::env::temp_dir().join(format!("ferrous_cli_home_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&home);
+        copy_dir(
+            Path::new("tests/fixtures/fake_home/.steam"),
+            &home.join(".steam"),
+        );
+        home
+    }
+
+    // One test, so nothing else in this binary reads the environment while
+    // `--home` rewrites it.
+    #[test]
+    fn test_scripted_apply_against_a_fixture_home() {
+        let home = scratch_home();
+        let data = home.join(".local/share/Paradox Interactive/Stellaris");
+        std::fs::create_dir_all(&data).unwrap();
+        std::fs::write(
+            data.join("dlc_load.json"),
+            r#"{"enabled_mods":[],"disabled_dlcs":[]}"#,
+        )
+        .unwrap();
+        let home_arg = home.to_str().unwrap();
+
+        run_args(&["--home", home_arg, "--json", "games"]).unwrap();
+        run_args(&["--home", home_arg, "--json", "mods", "stellaris"]).unwrap();
+
+        let mut collection = ModCollection::new("Scripted");
+        collection.add_mod("2001".to_string());
+        let file = home.join("scripted.json");
+        collection.save(&file).unwrap();
+        run_args(&[
+            "--home",
+            home_arg,
+            "collections",
+            "import",
+            "stellaris",
+            file.to_str().unwrap(),
+        ])
+        .unwrap();
+        run_args(&[
+            "--home",
+            home_arg,
+            "--json",
+            "collections",
+            "apply",
+            "stellaris",
+            "Scripted",
+        ])
+        .unwrap();
+
+        let written: DlcLoad =
+            serde_json::from_str(&std::fs::read_to_string(data.join("dlc_load.json")).unwrap())
+                .unwrap();
+        assert_eq!(written.enabled_mods, vec!["mod/ugc_2001.mod"]);
+        assert!(data.join("mod/ugc_2001.mod").is_file());
+        assert!(
+            run_args(&[
+                "--home",
+                home_arg,
+                "collections",
+                "apply",
+                "stellaris",
+                "Nope"
+            ])
+            .is_err()
+        );
+        let _ = std::fs::remove_dir_all(&home);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::errors::FileOperationError;
+use crate::errors::{FileOperationError, ParseLocation};
 use crate::fsutil::{MAX_READ_BYTES, read_to_string_limited, write_atomic};
 use serde::{Deserialize, Serialize};
 use serde_json;
@@ -45,21 +45,12 @@ impl ConflictCategory {
             _ => ConflictCategory::Other,
         }
     }
-
-    /// Whether a file in this category leaves the gameplay checksum untouched.
-    /// Paradox games keep achievements (and ironman saves) enabled only when
-    /// every active mod touches purely cosmetic content: graphics, sound, and
-    /// localisation text. Everything else — game data, defines, events, the map,
-    /// and unrecognised paths — changes the checksum and disables achievements.
-    pub fn is_achievement_safe(&self) -> bool {
-        matches!(
-            self,
-            ConflictCategory::Localisation | ConflictCategory::Gfx | ConflictCategory::Sound
-        )
-    }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// A parsed `.mod` descriptor. Keys this app doesn't model (game-specific or
+/// added by a future launcher) are kept in `extra`, the same way `DlcLoad`
+/// round-trips unknown fields, instead of being dropped on parse.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct ModDescriptor {
     pub name: Option<String>,              //Required
     pub path: Option<String>,              //Required
@@ -69,6 +60,35 @@ pub struct ModDescriptor {
     pub picture: Option<String>,
     pub version: Option<String>,
     pub dependencies: Option<Vec<String>>,
+    /// Mod-relative folders whose vanilla (and earlier mods') contents this mod
+    /// wipes before loading its own. The key may repeat, one folder per line.
+    pub replace_path: Option<Vec<String>>,
+    /// Separate user directory (saves, settings) the game uses while this mod
+    /// is active.
+    pub user_dir: Option<String>,
+    /// Zip archive holding the mod's content, used by older descriptors
+    /// instead of `path`.
+    pub archive: Option<String>,
+    /// The `.mod` file discovery read this from, relative to the game's data
+    /// folder the way `dlc_load.json` names it (e.g. `mod/ugc_123.mod`). Unset
+    /// for mods with no descriptor file of their own. Not part of the
+    /// descriptor file.
+    #[serde(default)]
+    pub descriptor_file: Option<String>,
+    /// The `id` from a Jomini-style `.metadata/metadata.json`. Not part of the
+    /// descriptor file.
+    #[serde(default)]
+    pub metadata_id: Option<String>,
+    /// Steam's download state for a workshop mod, joined in by discovery.
+    /// Not part of the descriptor file.
+    #[serde(default)]
+    pub workshop: Option<WorkshopItem>,
+    /// The mod root the content was found under, joined in by discovery. Not
+    /// part of the descriptor file.
+    #[serde(default)]
+    pub source: Option<LocationSource>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 impl ModDescriptor {
@@ -80,6 +100,28 @@ impl ModDescriptor {
     }
 }
 
+/// A `.mod` file discovery found but could not turn into a mod.
+#[derive(Debug, Serialize)]
+pub struct DescriptorDiagnostic {
+    pub file: PathBuf,
+    /// Human-readable reason, e.g. a missing required field or syntax error.
+    pub message: String,
+    /// Set for syntax errors, pointing at the offending line.
+    pub location: Option<ParseLocation>,
+}
+
+/// Everything mod discovery found for a game: the mods that parsed, plus a
+/// diagnostic for each descriptor that didn't, so the UI can report them
+/// instead of the mods silently disappearing.
+#[derive(Debug, Serialize, Default)]
+pub struct ModDiscovery {
+    pub mods: Vec<ModDescriptor>,
+    pub diagnostics: Vec<DescriptorDiagnostic>,
+    /// Set when Steam's `appworkshop_<appid>.acf` exists but can't be read;
+    /// the mods are still listed, just without their workshop state.
+    pub workshop_error: Option<String>,
+}
+
 #[derive(Debug)]
 pub struct LibraryVdf {
     pub idx: u32,
@@ -87,19 +129,257 @@ pub struct LibraryVdf {
     pub apps: Vec<u32>,
 }
 
+/// The fields we use from `steamapps/appmanifest_<appid>.acf`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppManifest {
+    pub app_id: u32,
+    pub name: Option<String>,
+    /// Folder under `steamapps/common/`. Authoritative over any name we'd guess.
+    pub install_dir: String,
+    pub state_flags: u32,
+    pub build_id: Option<u64>,
+    /// Unix timestamp of the last completed update.
+    pub last_updated: Option<u64>,
+}
+
+impl AppManifest {
+    /// Steam's `StateFlags` bit for "fully installed". Updates in progress or
+    /// queued set other bits alongside it; an uninstalled app clears it.
+    pub const FULLY_INSTALLED: u32 = 4;
+
+    pub fn is_installed(&self) -> bool {
+        self.state_flags & Self::FULLY_INSTALLED != 0
+    }
+}
+
+/// Where a subscribed workshop item stands, per `appworkshop_<appid>.acf`.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+pub enum WorkshopState {
+    Installed,
+    /// Installed, but Steam knows of a newer upload it hasn't fetched yet.
+    NeedsUpdate,
+    /// Subscribed but not installed yet.
+    Downloading,
+}
+
+/// One subscribed item from `steamapps/workshop/appworkshop_<appid>.acf`.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct WorkshopItem {
+    pub item_id: String,
+    pub state: WorkshopState,
+    /// Installed size in bytes.
+    pub size: Option<u64>,
+    /// Unix timestamp of the installed version.
+    pub time_updated: Option<u64>,
+    pub manifest: Option<String>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct DetectedGame {
     pub app_id: u32,
     pub install_path: String,
     pub game_name: String,
     pub paradox_data_path: String,
+    /// Raw `StateFlags` from the app manifest.
+    #[serde(default)]
+    pub state_flags: u32,
+    /// Steam build id of the installed version, from the app manifest.
+    #[serde(default)]
+    pub build_id: Option<u64>,
+    #[serde(default)]
+    pub last_updated: Option<u64>,
+    /// The `libraryfolders.vdf` the game was found through.
+    #[serde(default)]
+    pub source: Option<LocationSource>,
+}
+
+#[cfg(test)]
+impl DetectedGame {
+    /// An installed Stellaris (by name) with data under `paradox_data_path`
+    /// and no install folder; tests set the rest as they need it.
+    pub(crate) fn for_test(app_id: u32, paradox_data_path: impl AsRef<Path>) -> Self {
+        Self {
+            app_id,
+            install_path: String::new(),
+            game_name: "Stellaris".to_string(),
+            paradox_data_path: paradox_data_path.as_ref().to_string_lossy().into_owned(),
+            state_flags: 4,
+            build_id: None,
+            last_updated: None,
+            source: None,
+        }
+    }
+}
+
+/// Whether a location is one we look in by default or one the user added in
+/// `Settings`.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceKind {
+    Default,
+    Settings,
+}
+
+/// A Steam, Paradox or mod location and where we learned about it.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct LocationSource {
+    pub kind: SourceKind,
+    pub path: PathBuf,
+}
+
+/// How mods collide on a path.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictKind {
+    /// Several mods ship the same file; only one copy reaches the game.
+    FileOverride,
+    /// A later mod's `replace_path` wipes the whole folder, erasing every file
+    /// earlier mods put there. More severe than a file override: the earlier
+    /// mods lose all their content in that folder, not just one file.
+    DirectoryReplaced,
+}
+
+/// How the copies of a file that several mods ship compare byte for byte.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum ContentOverlap {
+    /// Every copy is the same, so load order doesn't matter. Not reported as a
+    /// conflict.
+    Identical,
+    /// No two copies are the same.
+    Differing,
+    /// Some copies match each other, others don't.
+    PartiallyIdentical,
 }
 
 #[derive(Debug, Serialize)]
 pub struct ModConflict {
+    /// The conflicting file, or for `DirectoryReplaced` the replaced folder.
     pub file_path: PathBuf,
     pub mod_list: Vec<String>,
     pub category: ConflictCategory,
+    pub kind: ConflictKind,
+    /// For `DirectoryReplaced`, the mod whose `replace_path` erases the folder
+    /// (also the last entry of `mod_list`).
+    pub replaced_by: Option<String>,
+    /// The mod whose copy the game ends up using.
+    pub winner: String,
+    /// Every other mod in `mod_list`, in load order: their copies are lost.
+    pub overridden: Vec<String>,
+    /// Whether `winner` is the last mod the collection loads at all, not just
+    /// the last of `mod_list`. Mods enabled after it don't ship this file, so
+    /// they don't change who wins unless they start to.
+    pub winner_is_last: bool,
+    /// For `FileOverride`, how the copies compare. Unset for
+    /// `DirectoryReplaced`, which is about whole folders.
+    pub content: Option<ContentOverlap>,
+}
+
+/// How the game settles two definitions of the same object in a folder.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum OverrideRule {
+    /// The definition in the file that sorts last wins (most of `common/`).
+    LastWins,
+    /// The definition in the file that sorts first wins (events).
+    FirstWins,
+    /// Both definitions were in same-named files, so the later mod's file
+    /// replaced the other whole before any objects were read.
+    FileReplace,
+}
+
+/// One mod's definition of an object.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct ObjectDefinition {
+    #[serde(rename = "mod")]
+    pub mod_name: String,
+    /// Mod-relative file the object is defined in.
+    pub file: PathBuf,
+}
+
+/// Two or more mods defining the same top-level object (a trait, an event
+/// id, ...) in one folder, whatever files they put it in.
+#[derive(Debug, Serialize)]
+pub struct ObjectConflict {
+    /// Mod-relative folder the object lives in, e.g. `common/traits`.
+    pub folder: PathBuf,
+    /// The object's key, or for events its id.
+    pub key: String,
+    pub category: ConflictCategory,
+    pub rule: OverrideRule,
+    /// Every mod's definition, in load order.
+    pub definitions: Vec<ObjectDefinition>,
+    /// The mod whose definition the game uses.
+    pub winner: String,
+    /// Every other mod in `definitions`, in load order.
+    pub overridden: Vec<String>,
+}
+
+/// One mod's text for a localisation key.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct LocalisationDefinition {
+    #[serde(rename = "mod")]
+    pub mod_name: String,
+    /// Mod-relative `.yml` file the key is in.
+    pub file: PathBuf,
+    pub value: String,
+}
+
+/// A localisation key that several mods define for the same language.
+#[derive(Debug, Serialize)]
+pub struct LocalisationConflict {
+    pub language: String,
+    pub key: String,
+    /// Every mod's definition, in load order.
+    pub definitions: Vec<LocalisationDefinition>,
+    /// The mod whose text the game shows, and that text.
+    pub winner: String,
+    pub winning_value: String,
+}
+
+/// How much of a mod's english text one of its other languages has.
+#[derive(Debug, Serialize)]
+pub struct LanguageCoverage {
+    pub language: String,
+    /// Keys the mod defines in english but not in this language, sorted.
+    pub missing: Vec<String>,
+}
+
+/// A mod's localisation coverage. Only languages the mod ships at least one
+/// file for are listed.
+#[derive(Debug, Serialize)]
+pub struct ModLocalisationCoverage {
+    #[serde(rename = "mod")]
+    pub mod_name: String,
+    pub english_keys: usize,
+    pub languages: Vec<LanguageCoverage>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LocalisationReport {
+    pub conflicts: Vec<LocalisationConflict>,
+    pub coverage: Vec<ModLocalisationCoverage>,
+}
+
+/// A mod file that replaces one of the game's own files.
+#[derive(Debug, Serialize)]
+pub struct VanillaOverride {
+    pub file_path: PathBuf,
+    pub category: ConflictCategory,
+    #[serde(rename = "mod")]
+    pub mod_name: String,
+    /// The game's copy was modified after the mod's, typically by a patch the
+    /// mod hasn't caught up with.
+    pub vanilla_newer: bool,
+    /// The mod doesn't ship the file: its `replace_path` deletes the game's
+    /// copy outright.
+    pub wiped: bool,
+}
+
+/// Everything `conflict_detection` finds for a collection.
+#[derive(Debug, Serialize)]
+pub struct ConflictReport {
+    /// Files and folders several mods fight over.
+    pub conflicts: Vec<ModConflict>,
+    /// Game files the mods replace, sorted by path then load order.
+    pub vanilla_overrides: Vec<VanillaOverride>,
 }
 
 /// Whether a single mod keeps achievements (and ironman saves) enabled.
@@ -118,6 +398,11 @@ pub struct ModCollection {
     pub id: Uuid,
     pub name: String,
     pub mods: Vec<ModEntry>,
+    /// DLCs to switch off while this collection is applied, as the `.dlc`
+    /// paths `dlc_load.json` uses (e.g. `dlc/dlc001_symbols_of_domination/dlc001.dlc`).
+    /// `None` leaves the game's current DLC selection alone.
+    #[serde(default)]
+    pub disabled_dlcs: Option<Vec<String>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -132,6 +417,17 @@ impl ModCollection {
             id: Uuid::new_v4(),
             name: name.to_string(),
             mods: vec![],
+            disabled_dlcs: None,
+        }
+    }
+
+    /// Enable or disable a DLC for this collection, taking over the DLC
+    /// selection if the collection didn't manage one yet.
+    pub fn set_dlc_enabled(&mut self, dlc_file: &str, enabled: bool) {
+        let disabled = self.disabled_dlcs.get_or_insert_with(Vec::new);
+        disabled.retain(|d| d != dlc_file);
+        if !enabled {
+            disabled.push(dlc_file.to_string());
         }
     }
 
@@ -168,10 +464,11 @@ impl ModCollection {
     }
 }
 
-/// The game's `dlc_load.json`. Only `enabled_mods` is managed by this app;
-/// `disabled_dlcs` and any field a future game version adds are round-tripped
-/// untouched via `extra` instead of being silently dropped on write, and a
-/// file missing either known field still parses.
+/// The game's `dlc_load.json`. `enabled_mods` is always managed by this app and
+/// `disabled_dlcs` when the applied collection has a DLC selection; any field
+/// a future game version adds is round-tripped untouched via `extra` instead
+/// of being silently dropped on write, and a file missing either known field
+/// still parses.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DlcLoad {
     #[serde(default)]
@@ -182,6 +479,29 @@ pub struct DlcLoad {
     pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
+/// A DLC found under the game's `dlc/` folder, from its `.dlc` descriptor.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct DlcDescriptor {
+    /// The descriptor relative to the install folder, as `dlc_load.json`'s
+    /// `disabled_dlcs` names it.
+    pub file: String,
+    pub name: String,
+    pub steam_id: Option<String>,
+    pub category: Option<String>,
+    /// Whether owning the DLC changes the gameplay checksum. Descriptors that
+    /// don't say are assumed to.
+    pub affects_checksum: bool,
+}
+
+/// A game's current `dlc_load.json` read back as a collection. Entries that
+/// don't match any discovered descriptor are listed in `unresolved` rather
+/// than dropped silently.
+#[derive(Debug, Serialize)]
+pub struct DlcLoadImport {
+    pub collection: ModCollection,
+    pub unresolved: Vec<String>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -199,6 +519,20 @@ mod tests {
         std::fs::remove_file(&path).ok();
     }
 
+    #[test]
+    fn test_set_dlc_enabled_takes_over_selection() {
+        let mut collection = ModCollection::new("DLC");
+        assert_eq!(collection.disabled_dlcs, None);
+        collection.set_dlc_enabled("dlc/a/a.dlc", false);
+        collection.set_dlc_enabled("dlc/a/a.dlc", false);
+        assert_eq!(
+            collection.disabled_dlcs,
+            Some(vec!["dlc/a/a.dlc".to_string()])
+        );
+        collection.set_dlc_enabled("dlc/a/a.dlc", true);
+        assert_eq!(collection.disabled_dlcs, Some(vec![]));
+    }
+
     #[test]
     fn test_dlc_load_round_trip_preserves_unknown_fields() {
         // Fields this app doesn't model (added by the game/launcher) must
@@ -1,2 +1,10 @@
+pub mod appmanifest;
+pub mod appworkshop;
+pub mod binary_vdf;
+pub mod diagnostic;
+pub mod dlc_descriptor;
+pub mod localisation;
+pub mod metadata;
 pub mod mod_descriptor;
+pub mod script;
 pub mod vdf;
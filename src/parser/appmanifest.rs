@@ -0,0 +1,102 @@
+use crate::errors::VdfParseError;
+use crate::models::AppManifest;
+use crate::parser::vdf::{VdfValue, parse_keyvalues};
+
+fn number<T: std::str::FromStr<Err = std::num::ParseIntError>>(
+    state: &VdfValue,
+    key: &str,
+) -> Result<Option<T>, VdfParseError> {
+    state
+        .get(key)
+        .and_then(VdfValue::as_str)
+        .map(|s| s.parse::<T>())
+        .transpose()
+        .map_err(VdfParseError::from)
+}
+
+/// Parse an `appmanifest_<appid>.acf` file (`"AppState" { ... }`).
+pub fn parse_app_manifest(input: &str) -> Result<AppManifest, VdfParseError> {
+    let vdf = parse_keyvalues(input)?;
+    let state = vdf
+        .root()
+        .filter(|root| root.as_block().is_some())
+        .ok_or(VdfParseError::MissingField("AppState".to_string()))?;
+
+    let app_id = number(state, "appid")?.ok_or(VdfParseError::MissingField("appid".to_string()))?;
+    let install_dir = state
+        .get("installdir")
+        .and_then(VdfValue::as_str)
+        .filter(|dir| !dir.is_empty())
+        .ok_or(VdfParseError::MissingField("installdir".to_string()))?
+        .to_string();
+
+    Ok(AppManifest {
+        app_id,
+        name: state
+            .get("name")
+            .and_then(VdfValue::as_str)
+            .map(str::to_string),
+        install_dir,
+        state_flags: number(state, "StateFlags")?.unwrap_or(0),
+        build_id: number(state, "buildid")?,
+        last_updated: number(state, "LastUpdated")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STELLARIS: &str = r#""AppState"
+{
+	"appid"		"281990"
+	"Universe"		"1"
+	"name"		"Stellaris"
+	"StateFlags"		"4"
+	"installdir"		"Stellaris"
+	"LastUpdated"		"1715180617"
+	"SizeOnDisk"		"13962323188"
+	"buildid"		"14277519"
+	"InstalledDepots"
+	{
+		"281991"
+		{
+			"manifest"		"3954318633542937432"
+			"size"		"13962323188"
+		}
+	}
+	"UserConfig"
+	{
+		"language"		"english"
+	}
+}
+"#;
+
+    #[test]
+    fn test_parse_app_manifest() {
+        let manifest = parse_app_manifest(STELLARIS).unwrap();
+        assert_eq!(manifest.app_id, 281990);
+        assert_eq!(manifest.name.as_deref(), Some("Stellaris"));
+        assert_eq!(manifest.install_dir, "Stellaris");
+        assert_eq!(manifest.state_flags, 4);
+        assert_eq!(manifest.build_id, Some(14277519));
+        assert_eq!(manifest.last_updated, Some(1715180617));
+        assert!(manifest.is_installed());
+    }
+
+    #[test]
+    fn test_app_manifest_requires_installdir() {
+        let input = "\"AppState\"\n{\n\t\"appid\"\t\"281990\"\n\t\"StateFlags\"\t\"4\"\n}\n";
+        let err = parse_app_manifest(input).unwrap_err();
+        assert!(matches!(err, VdfParseError::MissingField(f) if f == "installdir"));
+    }
+
+    #[test]
+    fn test_app_manifest_update_in_progress_is_still_installed() {
+        let updating = STELLARIS.replace("\"StateFlags\"\t\t\"4\"", "\"StateFlags\"\t\t\"1030\"");
+        assert!(parse_app_manifest(&updating).unwrap().is_installed());
+
+        let removed = STELLARIS.replace("\"StateFlags\"\t\t\"4\"", "\"StateFlags\"\t\t\"1\"");
+        assert!(!parse_app_manifest(&removed).unwrap().is_installed());
+    }
+}
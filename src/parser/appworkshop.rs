@@ -0,0 +1,108 @@
+use crate::errors::VdfParseError;
+use crate::models::{WorkshopItem, WorkshopState};
+use crate::parser::vdf::{VdfValue, parse_keyvalues};
+
+fn text(item: Option<&VdfValue>, key: &str) -> Option<String> {
+    item?.get(key)?.as_str().map(str::to_string)
+}
+
+fn number(item: Option<&VdfValue>, key: &str) -> Result<Option<u64>, VdfParseError> {
+    text(item, key)
+        .map(|s| s.parse())
+        .transpose()
+        .map_err(VdfParseError::from)
+}
+
+/// Parse `steamapps/workshop/appworkshop_<appid>.acf` into one entry per
+/// subscribed or installed item, in file order.
+///
+/// `WorkshopItemsInstalled` lists what's on disk; `WorkshopItemDetails` lists
+/// subscriptions along with the latest upload Steam has seen. An item in the
+/// details but not installed is still downloading; an installed item whose
+/// latest manifest or timestamp differs from the installed one needs an update.
+pub fn parse_app_workshop(input: &str) -> Result<Vec<WorkshopItem>, VdfParseError> {
+    let vdf = parse_keyvalues(input)?;
+    let root = vdf
+        .root()
+        .filter(|root| root.as_block().is_some())
+        .ok_or(VdfParseError::MissingField("AppWorkshop".to_string()))?;
+    let installed = root
+        .get("WorkshopItemsInstalled")
+        .and_then(VdfValue::as_block)
+        .unwrap_or_default();
+    let details = root.get("WorkshopItemDetails");
+
+    let mut items = Vec::new();
+    for entry in installed {
+        let id = entry.key.as_ref();
+        let detail = details.and_then(|d| d.get(id));
+        let time_updated = number(Some(&entry.value), "timeupdated")?;
+        let manifest = text(Some(&entry.value), "manifest");
+
+        let latest_manifest = text(detail, "latest_manifest");
+        let latest_time = number(detail, "latest_timeupdated")?;
+        let stale = latest_manifest.is_some_and(|latest| Some(&latest) != manifest.as_ref())
+            || latest_time.is_some_and(|latest| Some(latest) > time_updated);
+
+        items.push(WorkshopItem {
+            item_id: id.to_string(),
+            state: if stale {
+                WorkshopState::NeedsUpdate
+            } else {
+                WorkshopState::Installed
+            },
+            size: number(Some(&entry.value), "size")?,
+            time_updated,
+            manifest,
+        });
+    }
+
+    for entry in details.and_then(VdfValue::as_block).unwrap_or_default() {
+        if installed.iter().any(|i| i.key == entry.key) {
+            continue;
+        }
+        items.push(WorkshopItem {
+            item_id: entry.key.to_string(),
+            state: WorkshopState::Downloading,
+            size: None,
+            time_updated: None,
+            manifest: None,
+        });
+    }
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_app_workshop_states() {
+        let input = std::fs::read_to_string(
+            "tests/fixtures/fake_home/library/steamapps/workshop/appworkshop_281990.acf",
+        )
+        .unwrap();
+        let items = parse_app_workshop(&input).unwrap();
+        let states: Vec<_> = items
+            .iter()
+            .map(|i| (i.item_id.as_str(), i.state))
+            .collect();
+        assert_eq!(
+            states,
+            vec![
+                ("1001", WorkshopState::Installed),
+                ("1002", WorkshopState::NeedsUpdate),
+                ("1003", WorkshopState::Downloading),
+            ]
+        );
+        assert_eq!(items[0].size, Some(52428800));
+        assert_eq!(items[0].time_updated, Some(1700000000));
+        assert_eq!(items[0].manifest.as_deref(), Some("111"));
+    }
+
+    #[test]
+    fn test_parse_app_workshop_without_items() {
+        let input = "\"AppWorkshop\"\n{\n\t\"appid\"\t\"281990\"\n}\n";
+        assert!(parse_app_workshop(input).unwrap().is_empty());
+    }
+}
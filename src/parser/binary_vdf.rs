@@ -0,0 +1,521 @@
+use crate::errors::BinaryVdfError;
+use nom::{
+    IResult, Offset, Parser,
+    bytes::complete::{tag, take, take_until},
+    combinator::verify,
+    error::{ErrorKind, ParseError},
+    multi::{count, many0},
+    number::complete::{le_f32, le_i32, le_i64, le_u8, le_u16, le_u32, le_u64},
+    sequence::terminated,
+};
+
+// Value type tags. `0x08` closes a map; some writers emit `0x0B` instead.
+const TYPE_MAP: u8 = 0x00;
+const TYPE_STRING: u8 = 0x01;
+const TYPE_INT32: u8 = 0x02;
+const TYPE_FLOAT32: u8 = 0x03;
+const TYPE_POINTER: u8 = 0x04;
+const TYPE_WIDE_STRING: u8 = 0x05;
+const TYPE_COLOR: u8 = 0x06;
+const TYPE_UINT64: u8 = 0x07;
+const TYPE_END: u8 = 0x08;
+const TYPE_INT64: u8 = 0x0A;
+const TYPE_END_ALT: u8 = 0x0B;
+
+// `appinfo.vdf` header magics. v29 moved every key into a string table at the
+// end of the file.
+const APPINFO_V27: u32 = 0x0756_4427;
+const APPINFO_V28: u32 = 0x0756_4428;
+const APPINFO_V29: u32 = 0x0756_4429;
+
+/// A binary KeyValues value. Unlike the text format, scalars are typed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BinaryValue {
+    Map(Vec<BinaryEntry>),
+    Str(String),
+    Int32(i32),
+    Float32(f32),
+    Pointer(u32),
+    Color(u32),
+    UInt64(u64),
+    Int64(i64),
+}
+
+impl BinaryValue {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            BinaryValue::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&[BinaryEntry]> {
+        match self {
+            BinaryValue::Map(entries) => Some(entries),
+            _ => None,
+        }
+    }
+
+    /// Any integer value, widened. Steam stores ids as `Int32` in some files
+    /// and `UInt64` in others, so callers shouldn't have to care which.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            BinaryValue::Int32(v) => Some(v.into()),
+            BinaryValue::Pointer(v) | BinaryValue::Color(v) => Some(v.into()),
+            BinaryValue::UInt64(v) => i64::try_from(v).ok(),
+            BinaryValue::Int64(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Value of the first entry named `key` in a map, matched
+    /// case-insensitively (`shortcuts.vdf` has both `AppName` and `appname`).
+    pub fn get(&self, key: &str) -> Option<&BinaryValue> {
+        self.as_map()?
+            .iter()
+            .find(|e| e.key.eq_ignore_ascii_case(key))
+            .map(|e| &e.value)
+    }
+
+    /// Follow a path of keys through nested maps.
+    pub fn get_path(&self, path: &[&str]) -> Option<&BinaryValue> {
+        path.iter().try_fold(self, |value, key| value.get(key))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinaryEntry {
+    pub key: String,
+    pub value: BinaryValue,
+}
+
+/// One app's section of `appinfo.vdf`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppInfo {
+    pub app_id: u32,
+    pub last_updated: u32,
+    pub change_number: u32,
+    /// Normally a single `appinfo` map holding `common`, `config`, `depots`...
+    pub data: Vec<BinaryEntry>,
+}
+
+impl AppInfo {
+    /// The `appinfo` root map, wrapped so `get`/`get_path` work on it.
+    pub fn root(&self) -> Option<&BinaryValue> {
+        self.data.first().map(|e| &e.value)
+    }
+}
+
+/// A non-Steam game from `userdata/<id>/config/shortcuts.vdf`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shortcut {
+    pub app_id: u32,
+    pub app_name: String,
+    pub exe: String,
+    pub start_dir: String,
+    pub launch_options: String,
+    pub tags: Vec<String>,
+}
+
+impl Shortcut {
+    /// The id Steam expects in `steam://rungameid/<id>` for this shortcut.
+    pub fn game_id(&self) -> u64 {
+        (u64::from(self.app_id) << 32) | 0x0200_0000
+    }
+}
+
+#[derive(Debug)]
+enum FaultKind {
+    Truncated,
+    UnknownType(u8),
+    BadStringIndex(u32),
+}
+
+/// nom error carrying the position and what went wrong there.
+#[derive(Debug)]
+struct Fault<'a> {
+    input: &'a [u8],
+    kind: FaultKind,
+}
+
+impl<'a> ParseError<&'a [u8]> for Fault<'a> {
+    fn from_error_kind(input: &'a [u8], _kind: ErrorKind) -> Self {
+        Fault {
+            input,
+            kind: FaultKind::Truncated,
+        }
+    }
+
+    fn append(_input: &'a [u8], _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+type BinResult<'a, O> = IResult<&'a [u8], O, Fault<'a>>;
+
+fn fault<'a>(input: &'a [u8], kind: FaultKind) -> nom::Err<Fault<'a>> {
+    nom::Err::Failure(Fault { input, kind })
+}
+
+/// Convert a nom error into one pointing at a byte offset of `source`.
+fn locate(source: &[u8], err: nom::Err<Fault>) -> BinaryVdfError {
+    let fault = match err {
+        nom::Err::Error(f) | nom::Err::Failure(f) => f,
+        nom::Err::Incomplete(_) => {
+            return BinaryVdfError::Truncated {
+                offset: source.len(),
+            };
+        }
+    };
+    let offset = source.offset(fault.input);
+    match fault.kind {
+        FaultKind::Truncated => BinaryVdfError::Truncated { offset },
+        FaultKind::UnknownType(tag) => BinaryVdfError::UnknownType { tag, offset },
+        FaultKind::BadStringIndex(index) => BinaryVdfError::BadStringIndex { index, offset },
+    }
+}
+
+/// NUL-terminated string. Steam doesn't promise UTF-8, so decode lossily.
+fn cstring(input: &[u8]) -> BinResult<'_, String> {
+    terminated(take_until(&b"\0"[..]), tag(&b"\0"[..]))
+        .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+        .parse(input)
+}
+
+/// NUL-terminated UTF-16LE string.
+fn wide_string(input: &[u8]) -> BinResult<'_, String> {
+    terminated(many0(verify(le_u16, |unit| *unit != 0)), tag(&b"\0\0"[..]))
+        .map(|units: Vec<u16>| String::from_utf16_lossy(&units))
+        .parse(input)
+}
+
+/// How keys are stored: inline as C strings, or (appinfo v29) as `u32`
+/// indices into a string table.
+enum Keys<'t> {
+    Inline,
+    Table(&'t [String]),
+}
+
+impl Keys<'_> {
+    fn key<'a>(&self, input: &'a [u8]) -> BinResult<'a, String> {
+        match self {
+            Keys::Inline => cstring(input),
+            Keys::Table(strings) => {
+                let (rest, index) = le_u32(input)?;
+                let key = strings
+                    .get(index as usize)
+                    .ok_or_else(|| fault(input, FaultKind::BadStringIndex(index)))?;
+                Ok((rest, key.clone()))
+            }
+        }
+    }
+
+    /// The value after a key. `tag_at` is the type tag's position, which is
+    /// where an unknown type gets reported.
+    fn value<'a>(
+        &self,
+        type_tag: u8,
+        tag_at: &'a [u8],
+        input: &'a [u8],
+    ) -> BinResult<'a, BinaryValue> {
+        match type_tag {
+            TYPE_MAP => self
+                .entries(input, false)
+                .map(|(r, v)| (r, BinaryValue::Map(v))),
+            TYPE_STRING => cstring.map(BinaryValue::Str).parse(input),
+            TYPE_WIDE_STRING => wide_string.map(BinaryValue::Str).parse(input),
+            TYPE_INT32 => le_i32.map(BinaryValue::Int32).parse(input),
+            TYPE_FLOAT32 => le_f32.map(BinaryValue::Float32).parse(input),
+            TYPE_POINTER => le_u32.map(BinaryValue::Pointer).parse(input),
+            TYPE_COLOR => le_u32.map(BinaryValue::Color).parse(input),
+            TYPE_UINT64 => le_u64.map(BinaryValue::UInt64).parse(input),
+            TYPE_INT64 => le_i64.map(BinaryValue::Int64).parse(input),
+            _ => Err(fault(tag_at, FaultKind::UnknownType(type_tag))),
+        }
+    }
+
+    /// Entries up to and including the closing end tag. At the root, running
+    /// out of input also ends the list, since not every writer closes it.
+    fn entries<'a>(&self, mut input: &'a [u8], root: bool) -> BinResult<'a, Vec<BinaryEntry>> {
+        let mut entries = Vec::new();
+        loop {
+            if root && input.is_empty() {
+                return Ok((input, entries));
+            }
+            let (rest, type_tag) = le_u8(input)?;
+            if type_tag == TYPE_END || type_tag == TYPE_END_ALT {
+                return Ok((rest, entries));
+            }
+            let (rest, key) = self.key(rest)?;
+            let (rest, value) = self.value(type_tag, input, rest)?;
+            entries.push(BinaryEntry { key, value });
+            input = rest;
+        }
+    }
+}
+
+/// Parse a binary KeyValues document with inline keys, such as
+/// `shortcuts.vdf`.
+pub fn parse_binary_vdf(bytes: &[u8]) -> Result<Vec<BinaryEntry>, BinaryVdfError> {
+    Keys::Inline
+        .entries(bytes, true)
+        .map(|(_, entries)| entries)
+        .map_err(|e| locate(bytes, e))
+}
+
+fn string_table(input: &[u8]) -> BinResult<'_, Vec<String>> {
+    let (rest, len) = le_u32(input)?;
+    count(cstring, len as usize).parse(rest)
+}
+
+/// `app_id, size, body` where the body holds the fixed header then the KV data.
+fn appinfo_app<'a>(input: &'a [u8], version: u32, keys: &Keys) -> BinResult<'a, AppInfo> {
+    let (rest, app_id) = le_u32(input)?;
+    let (rest, size) = le_u32(rest)?;
+    let (rest, body) = take(size).parse(rest)?;
+
+    let (body, _info_state) = le_u32(body)?;
+    let (body, last_updated) = le_u32(body)?;
+    let (body, _pics_token) = le_u64(body)?;
+    let (body, _sha1) = take(20usize).parse(body)?;
+    let (body, change_number) = le_u32(body)?;
+    let body = if version >= APPINFO_V28 {
+        take(20usize).parse(body)?.0
+    } else {
+        body
+    };
+    let (_, data) = keys.entries(body, true)?;
+
+    Ok((
+        rest,
+        AppInfo {
+            app_id,
+            last_updated,
+            change_number,
+            data,
+        },
+    ))
+}
+
+/// Step over one app's `app_id, size, body` without parsing the body.
+fn skip_appinfo_app(input: &[u8]) -> BinResult<'_, ()> {
+    let (rest, _app_id) = le_u32(input)?;
+    let (rest, size) = le_u32(rest)?;
+    let (rest, _body) = take(size).parse(rest)?;
+    Ok((rest, ()))
+}
+
+/// Parse Steam's `appcache/appinfo.vdf` (versions 27, 28 and 29).
+pub fn parse_appinfo(bytes: &[u8]) -> Result<Vec<AppInfo>, BinaryVdfError> {
+    walk_appinfo(bytes, |_| true)
+}
+
+/// The app with `app_id` from `appinfo.vdf`, if it's there. Every other app
+/// is stepped over by its size, without building its KeyValues, so looking up
+/// one game stays cheap however many apps the client has seen.
+pub fn find_appinfo(bytes: &[u8], app_id: u32) -> Result<Option<AppInfo>, BinaryVdfError> {
+    Ok(walk_appinfo(bytes, |id| id == app_id)?.into_iter().next())
+}
+
+/// The apps in `appinfo.vdf` that `wanted` picks by app id, parsed.
+fn walk_appinfo(
+    bytes: &[u8],
+    wanted: impl Fn(u32) -> bool,
+) -> Result<Vec<AppInfo>, BinaryVdfError> {
+    let fail = |e| locate(bytes, e);
+    let (rest, version) = le_u32(bytes).map_err(fail)?;
+    if !matches!(version, APPINFO_V27 | APPINFO_V28 | APPINFO_V29) {
+        return Err(BinaryVdfError::UnsupportedVersion(version));
+    }
+    let (mut rest, _universe) = le_u32(rest).map_err(fail)?;
+
+    let strings = if version >= APPINFO_V29 {
+        let (after, table_offset) = le_u64(rest).map_err(fail)?;
+        rest = after;
+        let table = usize::try_from(table_offset)
+            .ok()
+            .and_then(|offset| bytes.get(offset..))
+            .ok_or(BinaryVdfError::Truncated {
+                offset: bytes.len(),
+            })?;
+        Some(string_table(table).map_err(fail)?.1)
+    } else {
+        None
+    };
+    let keys = match &strings {
+        Some(strings) => Keys::Table(strings),
+        None => Keys::Inline,
+    };
+
+    let mut apps = Vec::new();
+    loop {
+        let (_, app_id) = le_u32(rest).map_err(fail)?;
+        if app_id == 0 {
+            return Ok(apps);
+        }
+        if wanted(app_id) {
+            let (after, app) = appinfo_app(rest, version, &keys).map_err(fail)?;
+            apps.push(app);
+            rest = after;
+        } else {
+            rest = skip_appinfo_app(rest).map_err(fail)?.0;
+        }
+    }
+}
+
+/// Parse `shortcuts.vdf` into its non-Steam games. Entries without an app id
+/// (written by very old clients) are skipped.
+pub fn parse_shortcuts(bytes: &[u8]) -> Result<Vec<Shortcut>, BinaryVdfError> {
+    let root = BinaryValue::Map(parse_binary_vdf(bytes)?);
+    let text = |shortcut: &BinaryValue, key: &str| {
+        shortcut
+            .get(key)
+            .and_then(BinaryValue::as_str)
+            .unwrap_or_default()
+            .to_string()
+    };
+    Ok(root
+        .get("shortcuts")
+        .and_then(BinaryValue::as_map)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|entry| {
+            let shortcut = &entry.value;
+            // Stored as a signed int32; the bits are the unsigned id.
+            let app_id = shortcut.get("appid")?.as_i64()? as u32;
+            Some(Shortcut {
+                app_id,
+                app_name: text(shortcut, "AppName"),
+                exe: text(shortcut, "Exe"),
+                start_dir: text(shortcut, "StartDir"),
+                launch_options: text(shortcut, "LaunchOptions"),
+                tags: shortcut
+                    .get("tags")
+                    .and_then(BinaryValue::as_map)
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|t| t.value.as_str().map(str::to_string))
+                    .collect(),
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn fixture(name: &str) -> Vec<u8> {
+        fs::read(format!("tests/fixtures/binary_vdf/{name}")).unwrap()
+    }
+
+    #[test]
+    fn test_parse_binary_vdf_typed_values() {
+        let mut bytes = vec![TYPE_MAP];
+        bytes.extend(b"root\0");
+        bytes.push(TYPE_STRING);
+        bytes.extend(b"name\0Stellaris\0");
+        bytes.push(TYPE_INT32);
+        bytes.extend(b"appid\0");
+        bytes.extend(281990i32.to_le_bytes());
+        bytes.push(TYPE_UINT64);
+        bytes.extend(b"big\0");
+        bytes.extend(u64::MAX.to_le_bytes());
+        bytes.push(TYPE_WIDE_STRING);
+        bytes.extend(b"wide\0");
+        bytes.extend(b"h\0i\0\0\0");
+        bytes.extend([TYPE_END, TYPE_END]);
+
+        let entries = parse_binary_vdf(&bytes).unwrap();
+        let root = &entries[0].value;
+        assert_eq!(root.get("NAME").unwrap().as_str(), Some("Stellaris"));
+        assert_eq!(root.get("appid").unwrap().as_i64(), Some(281990));
+        assert_eq!(root.get("big"), Some(&BinaryValue::UInt64(u64::MAX)));
+        assert_eq!(root.get("big").unwrap().as_i64(), None);
+        assert_eq!(root.get("wide").unwrap().as_str(), Some("hi"));
+    }
+
+    #[test]
+    fn test_parse_binary_vdf_reports_offsets() {
+        let mut bytes = vec![TYPE_MAP];
+        bytes.extend(b"root\0");
+        bytes.push(0x42);
+        bytes.extend(b"key\0");
+        assert_eq!(
+            parse_binary_vdf(&bytes),
+            Err(BinaryVdfError::UnknownType {
+                tag: 0x42,
+                offset: 6
+            })
+        );
+
+        let truncated = [TYPE_INT32, b'k', 0, 1, 2];
+        assert_eq!(
+            parse_binary_vdf(&truncated),
+            Err(BinaryVdfError::Truncated { offset: 3 })
+        );
+    }
+
+    #[test]
+    fn test_parse_shortcuts_fixture() {
+        let shortcuts = parse_shortcuts(&fixture("shortcuts.vdf")).unwrap();
+        assert_eq!(shortcuts.len(), 2);
+        assert_eq!(shortcuts[0].app_name, "Stellaris (GOG)");
+        assert_eq!(shortcuts[0].exe, "\"/games/stellaris/stellaris\"");
+        assert_eq!(shortcuts[0].launch_options, "-gdpr-compliant");
+        assert_eq!(shortcuts[0].tags, vec!["Paradox", "Strategy"]);
+        // Negative int32 on disk.
+        assert_eq!(shortcuts[1].app_id, 0xA1B2_C3D4);
+        assert_eq!(shortcuts[1].game_id(), 0xA1B2_C3D4_0200_0000);
+    }
+
+    #[test]
+    fn test_parse_appinfo_v28_fixture() {
+        let apps = parse_appinfo(&fixture("appinfo_v28.vdf")).unwrap();
+        assert_eq!(apps.len(), 2);
+        assert_eq!(apps[0].app_id, 281990);
+        assert_eq!(apps[0].change_number, 21000000);
+        let root = apps[0].root().unwrap();
+        assert_eq!(
+            root.get_path(&["common", "name"]).unwrap().as_str(),
+            Some("Stellaris")
+        );
+        assert_eq!(
+            root.get_path(&["config", "launch", "0", "executable"])
+                .unwrap()
+                .as_str(),
+            Some("stellaris")
+        );
+        assert_eq!(apps[1].app_id, 1158310);
+    }
+
+    #[test]
+    fn test_parse_appinfo_v29_string_table_fixture() {
+        let v28 = parse_appinfo(&fixture("appinfo_v28.vdf")).unwrap();
+        let v29 = parse_appinfo(&fixture("appinfo_v29.vdf")).unwrap();
+        assert_eq!(v28, v29);
+    }
+
+    #[test]
+    fn test_find_appinfo_parses_only_the_wanted_app() {
+        for name in ["appinfo_v28.vdf", "appinfo_v29.vdf"] {
+            let bytes = fixture(name);
+            let all = parse_appinfo(&bytes).unwrap();
+            assert_eq!(
+                find_appinfo(&bytes, 1158310).unwrap().as_ref(),
+                Some(&all[1])
+            );
+            assert_eq!(find_appinfo(&bytes, 999999).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn test_parse_appinfo_rejects_unknown_magic() {
+        let bytes = [0xDE, 0xAD, 0xBE, 0xEF, 1, 0, 0, 0];
+        assert_eq!(
+            parse_appinfo(&bytes),
+            Err(BinaryVdfError::UnsupportedVersion(0xEFBE_ADDE))
+        );
+    }
+}
@@ -0,0 +1,86 @@
+use crate::errors::ParseLocation;
+use nom::error::{ErrorKind, ParseError};
+use nom::{IResult, Offset, Parser};
+
+/// nom error that remembers what the parser was looking for where it failed,
+/// so syntax errors can say "expected `}`" rather than echoing nom internals.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expected<'a> {
+    pub input: &'a str,
+    pub expected: String,
+}
+
+pub type ParseResult<'a, O> = IResult<&'a str, O, Expected<'a>>;
+
+impl<'a> Expected<'a> {
+    /// Where in `source` (which `input` must be a suffix of) the failure is.
+    pub fn locate(&self, source: &str) -> ParseLocation {
+        // At end of input nom may hand back an empty slice that doesn't point
+        // into `source`, so don't trust its address.
+        let offset = if self.input.is_empty() {
+            source.len()
+        } else {
+            source.offset(self.input)
+        };
+        ParseLocation::at(source, offset, self.expected.as_str())
+    }
+}
+
+impl<'a> ParseError<&'a str> for Expected<'a> {
+    fn from_error_kind(input: &'a str, kind: ErrorKind) -> Self {
+        Expected {
+            input,
+            expected: kind.description().to_lowercase(),
+        }
+    }
+
+    fn append(_: &'a str, _: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    fn from_char(input: &'a str, c: char) -> Self {
+        Expected {
+            input,
+            expected: format!("`{c}`"),
+        }
+    }
+
+    /// Between failed alternatives, report the one that got furthest.
+    fn or(self, other: Self) -> Self {
+        if other.input.len() < self.input.len() {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+/// Run `parser` and, if it fails to match, fail hard (no backtracking) with
+/// `what` as the expectation. Used once the grammar has committed, e.g. after
+/// an opening brace, so the error points at the real problem instead of
+/// wherever an enclosing `alt` gave up.
+pub fn required<'a, O, P>(
+    mut parser: P,
+    what: &'static str,
+) -> impl FnMut(&'a str) -> ParseResult<'a, O>
+where
+    P: Parser<&'a str, Output = O, Error = Expected<'a>>,
+{
+    move |input| {
+        parser.parse(input).map_err(|e| match e {
+            nom::Err::Error(err) => nom::Err::Failure(Expected {
+                input: err.input,
+                expected: what.to_string(),
+            }),
+            other => other,
+        })
+    }
+}
+
+/// Convert a nom error into a location in `source`.
+pub fn locate(source: &str, err: nom::Err<Expected<'_>>) -> ParseLocation {
+    match err {
+        nom::Err::Error(e) | nom::Err::Failure(e) => e.locate(source),
+        nom::Err::Incomplete(_) => ParseLocation::at(source, source.len(), "more input"),
+    }
+}
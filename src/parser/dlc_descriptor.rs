@@ -0,0 +1,60 @@
+use crate::errors::ModParseError;
+use crate::models::DlcDescriptor;
+use crate::parser::script;
+
+/// Parse a DLC's `.dlc` descriptor. These use the same script syntax as `.mod`
+/// files; only `name` is required. `file` is where the caller found it, and
+/// is copied into the result as given.
+pub fn parse_dlc_file(input: &str, file: &str) -> Result<DlcDescriptor, ModParseError> {
+    let parsed = script::parse_script(input)?;
+    let text = |key: &str| {
+        parsed
+            .root
+            .get(key)
+            .and_then(|v| v.text())
+            .map(|v| v.into_owned())
+    };
+
+    let name = text("name")
+        .filter(|n| !n.trim().is_empty())
+        .ok_or(ModParseError::MissingField("name".to_string()))?;
+    Ok(DlcDescriptor {
+        file: file.to_string(),
+        name,
+        steam_id: text("steam_id"),
+        category: text("category"),
+        affects_checksum: text("affects_checksum").is_none_or(|v| v != "no"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dlc_file() {
+        let input = r#"name = "Leviathans Story Pack"
+archive = "dlc/dlc004_leviathans/dlc004.zip"
+steam_id = "518910"
+category = "content"
+affects_checksum = yes
+"#;
+        let dlc = parse_dlc_file(input, "dlc/dlc004_leviathans/dlc004.dlc").unwrap();
+        assert_eq!(dlc.name, "Leviathans Story Pack");
+        assert_eq!(dlc.file, "dlc/dlc004_leviathans/dlc004.dlc");
+        assert_eq!(dlc.steam_id.as_deref(), Some("518910"));
+        assert_eq!(dlc.category.as_deref(), Some("content"));
+        assert!(dlc.affects_checksum);
+    }
+
+    #[test]
+    fn test_parse_dlc_file_checksum_exempt_and_required_name() {
+        let dlc =
+            parse_dlc_file("name = \"Soundtrack\"\naffects_checksum = no\n", "x.dlc").unwrap();
+        assert!(!dlc.affects_checksum);
+        assert!(matches!(
+            parse_dlc_file("steam_id = \"1\"", "x.dlc"),
+            Err(ModParseError::MissingField(f)) if f == "name"
+        ));
+    }
+}
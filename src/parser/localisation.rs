@@ -0,0 +1,151 @@
+use crate::errors::{LocalisationParseError, ParseLocation};
+use crate::parser::diagnostic::{Expected, ParseResult, locate, required};
+use nom::{
+    Offset, Parser,
+    branch::alt,
+    bytes::complete::{is_not, tag, take_while, take_while1},
+    character::complete::{char, digit1, multispace1, space0},
+    combinator::{opt, recognize},
+    multi::{many0, many0_count},
+    sequence::{preceded, terminated},
+};
+
+/// `KEY:0 "value"`. The version number is optional and the value is kept as
+/// written, with `\n`, `§` colour codes and `$VARIABLES$` left in place.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalisationEntry<'a> {
+    pub key: &'a str,
+    pub version: Option<u32>,
+    pub value: &'a str,
+}
+
+/// A parsed localisation `.yml` file: one language header (`l_english:`) and
+/// its entries in source order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Localisation<'a> {
+    /// The language without its `l_` prefix, e.g. `english`.
+    pub language: &'a str,
+    pub entries: Vec<LocalisationEntry<'a>>,
+}
+
+/// Whitespace and `#` line comments, in any quantity (including none).
+fn skip_trivia(input: &str) -> ParseResult<'_, &str> {
+    recognize(many0_count(alt((
+        multispace1,
+        recognize(preceded(char('#'), take_while(|c| c != '\n'))),
+    ))))
+    .parse(input)
+}
+
+fn is_key_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '.' | '-' | '\'')
+}
+
+fn header(input: &str) -> ParseResult<'_, &str> {
+    terminated(
+        preceded(tag("l_"), take_while1(is_key_char)),
+        required(char(':'), "`:` after the language"),
+    )
+    .parse(input)
+}
+
+/// A quoted value running to the last `"` on its line. The games don't
+/// escape inner quotes, so `"Say "hi""` is one value.
+fn value(input: &str) -> ParseResult<'_, &str> {
+    let (rest, _) = char('"').parse(input)?;
+    let line = rest.split(['\n', '\r']).next().unwrap_or_default();
+    match line.rfind('"') {
+        Some(end) => Ok((&rest[end + 1..], &rest[..end])),
+        None => Err(nom::Err::Failure(Expected {
+            input: &rest[line.len()..],
+            expected: "a closing `\"`".to_string(),
+        })),
+    }
+}
+
+fn entry(input: &str) -> ParseResult<'_, LocalisationEntry<'_>> {
+    let (input, key) = take_while1(is_key_char).parse(input)?;
+    let (input, _) = required(char(':'), "`:` after the key").parse(input)?;
+    let (input, version) = opt(digit1).parse(input)?;
+    let (input, _) = space0.parse(input)?;
+    let (input, value) = required(value, "a quoted value").parse(input)?;
+    let (input, _) = space0.parse(input)?;
+    let (input, _) = opt(is_not("\n")).parse(input)?;
+    Ok((
+        input,
+        LocalisationEntry {
+            key,
+            version: version.and_then(|v| v.parse().ok()),
+            value,
+        },
+    ))
+}
+
+/// Parse a Paradox localisation file. These look like YAML but aren't: no
+/// nesting, optional version numbers after the colon, and unescaped quotes in
+/// values, so they get their own parser. Anything after a value's closing
+/// quote on the same line is ignored, as the games do with trailing comments.
+pub fn parse_localisation(input: &str) -> Result<Localisation<'_>, LocalisationParseError> {
+    let (rest, (language, entries)) = preceded(
+        skip_trivia,
+        (
+            required(header, "a language header such as `l_english:`"),
+            preceded(skip_trivia, many0(terminated(entry, skip_trivia))),
+        ),
+    )
+    .parse(input)
+    .map_err(|e| LocalisationParseError::ParseError(locate(input, e)))?;
+    if !rest.is_empty() {
+        return Err(LocalisationParseError::ParseError(ParseLocation::at(
+            input,
+            input.offset(rest),
+            "a `KEY: \"value\"` line",
+        )));
+    }
+    Ok(Localisation { language, entries })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_localisation() {
+        let input = "l_english:\n # Traits\n trait_strong:0 \"Strong\"\n trait_strong_desc: \"Say \"hi\" §Ytwice§!\" # note\n\n EMPTY:1 \"\"\n";
+        let loc = parse_localisation(input).unwrap();
+        assert_eq!(loc.language, "english");
+        assert_eq!(
+            loc.entries,
+            vec![
+                LocalisationEntry {
+                    key: "trait_strong",
+                    version: Some(0),
+                    value: "Strong",
+                },
+                LocalisationEntry {
+                    key: "trait_strong_desc",
+                    version: None,
+                    value: "Say \"hi\" §Ytwice§!",
+                },
+                LocalisationEntry {
+                    key: "EMPTY",
+                    version: Some(1),
+                    value: "",
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_localisation_errors_point_at_the_line() {
+        let LocalisationParseError::ParseError(location) =
+            parse_localisation("l_german:\n ok:0 \"Gut\"\n broken:0 \"no end\n").unwrap_err();
+        assert_eq!(location.line, 3);
+        assert_eq!(location.expected, "a closing `\"`");
+
+        let LocalisationParseError::ParseError(location) =
+            parse_localisation("trait:0 \"x\"\n").unwrap_err();
+        assert_eq!(location.line, 1);
+        assert_eq!(location.expected, "a language header such as `l_english:`");
+    }
+}
@@ -0,0 +1,126 @@
+use crate::errors::ModParseError;
+use crate::models::ModDescriptor;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct Relationship {
+    #[serde(default)]
+    rel_type: String,
+    #[serde(default)]
+    id: String,
+    display_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GameCustomData {
+    #[serde(default)]
+    replace_paths: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Metadata {
+    name: Option<String>,
+    id: Option<String>,
+    version: Option<String>,
+    supported_game_version: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    relationships: Vec<Relationship>,
+    game_custom_data: Option<GameCustomData>,
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.filter(|v| !v.trim().is_empty())
+}
+
+/// Parse a Jomini-style `.metadata/metadata.json` into a descriptor. The file
+/// doesn't say where the mod lives, so `path` is left for the caller to set.
+///
+/// Dependencies come from `relationships` entries with `rel_type` of
+/// `dependency`, named by `display_name` when present so they resolve the
+/// same way `.mod` dependencies do.
+pub fn parse_metadata_json(input: &str) -> Result<ModDescriptor, ModParseError> {
+    let metadata: Metadata = serde_json::from_str(input)?;
+    let name = non_empty(metadata.name).ok_or(ModParseError::MissingField("name".to_string()))?;
+
+    let dependencies: Vec<String> = metadata
+        .relationships
+        .into_iter()
+        .filter(|r| r.rel_type == "dependency")
+        .filter_map(|r| non_empty(r.display_name).or(non_empty(Some(r.id))))
+        .collect();
+    let replace_path = metadata
+        .game_custom_data
+        .map(|data| data.replace_paths)
+        .filter(|paths| !paths.is_empty());
+
+    Ok(ModDescriptor {
+        name: Some(name),
+        metadata_id: non_empty(metadata.id),
+        version: non_empty(metadata.version),
+        supported_version: non_empty(metadata.supported_game_version),
+        tags: Some(metadata.tags).filter(|tags| !tags.is_empty()),
+        dependencies: Some(dependencies).filter(|deps| !deps.is_empty()),
+        replace_path,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_metadata_json() {
+        let input = r#"{
+            "name": "Better Politics",
+            "id": "better.politics",
+            "version": "1.2",
+            "supported_game_version": "1.5.*",
+            "short_description": "",
+            "tags": ["Gameplay", "Politics"],
+            "relationships": [
+                { "rel_type": "dependency", "id": "community.framework", "display_name": "Community Framework", "resource_type": "mod", "version": "1.*" },
+                { "rel_type": "dependency", "id": "ui.library", "resource_type": "mod" },
+                { "rel_type": "partner", "id": "other.mod", "resource_type": "mod" }
+            ],
+            "game_custom_data": { "multiplayer_synchronized": true, "replace_paths": ["common/laws"] }
+        }"#;
+        let descriptor = parse_metadata_json(input).unwrap();
+        assert_eq!(descriptor.name.as_deref(), Some("Better Politics"));
+        assert_eq!(descriptor.metadata_id.as_deref(), Some("better.politics"));
+        assert_eq!(descriptor.version.as_deref(), Some("1.2"));
+        assert_eq!(descriptor.supported_version.as_deref(), Some("1.5.*"));
+        assert_eq!(
+            descriptor.tags,
+            Some(vec!["Gameplay".to_string(), "Politics".to_string()])
+        );
+        assert_eq!(
+            descriptor.dependencies,
+            Some(vec![
+                "Community Framework".to_string(),
+                "ui.library".to_string()
+            ])
+        );
+        assert_eq!(
+            descriptor.replace_path,
+            Some(vec!["common/laws".to_string()])
+        );
+        assert_eq!(descriptor.path, None);
+    }
+
+    #[test]
+    fn test_parse_metadata_json_requires_name() {
+        let result = parse_metadata_json(r#"{ "name": "", "id": "x" }"#);
+        assert!(matches!(result, Err(ModParseError::MissingField(f)) if f == "name"));
+    }
+
+    #[test]
+    fn test_parse_metadata_json_rejects_invalid_json() {
+        assert!(matches!(
+            parse_metadata_json("{ \"name\": "),
+            Err(ModParseError::Json(_))
+        ));
+    }
+}
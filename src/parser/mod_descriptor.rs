@@ -1,94 +1,49 @@
 use crate::errors::ModParseError;
+use crate::fsutil::{MAX_READ_BYTES, encode_text, read_text_limited, write_atomic};
 use crate::models::ModDescriptor;
-use nom::branch::alt;
-use nom::character::complete::multispace0;
-use nom::combinator::map;
-use nom::multi::many0;
-use nom::sequence::preceded;
-use nom::{
-    IResult, Parser, bytes::complete::is_not, character::complete::char, sequence::delimited,
-    sequence::separated_pair,
-};
-
-#[derive(Debug)]
+use crate::parser::script::{self, ScriptValue};
+use std::ops::Range;
+use std::path::Path;
+
+/// A descriptor field's value as the writer sees it.
+#[derive(Debug, PartialEq)]
 pub enum ModValue<'a> {
     Single(&'a str),
-    List(Vec<&'a str>),
-}
-
-// Parses a key value string i.e intro="Hello, World!" -> (intro, Hello, World!)
-pub fn parse_key_value(input: &str) -> IResult<&str, (&str, &str)> {
-    separated_pair(
-        is_not("="),
-        char('='),
-        delimited(char('"'), is_not("\""), char('"')),
-    )
-    .parse(input)
-}
-
-// Parses a brace delimited tag block i.e tags={ "World", "Other" } -> (tags, [World, Other])
-pub fn parse_block_value(input: &str) -> IResult<&str, (&str, Vec<&str>)> {
-    separated_pair(
-        is_not("="),
-        char('='),
-        delimited(
-            char('{'),
-            many0(preceded(
-                multispace0,
-                delimited(char('"'), is_not("\""), char('"')),
-            )),
-            preceded(multispace0, char('}')),
-        ),
-    )
-    .parse(input)
+    List(&'a [String]),
+    /// An unmodelled key carried in [`ModDescriptor::extra`].
+    Json(&'a serde_json::Value),
 }
 
-pub fn parse_mod_file(input: &str) -> Result<ModDescriptor, ModParseError> {
-    let mut mod_descriptor = ModDescriptor {
-        name: None,
-        path: None,
-        remote_file_id: None,
-        supported_version: None,
-        tags: None,
-        picture: None,
-        version: None,
-        dependencies: None,
-    };
+fn single(value: &ScriptValue) -> Option<String> {
+    value.text().map(|v| v.into_owned())
+}
 
-    let parsed_file = many0(preceded(
-        multispace0,
-        alt((
-            map(parse_block_value, |(k, v)| (k, ModValue::List(v))),
-            map(parse_key_value, |(k, v)| (k, ModValue::Single(v))),
-        )),
-    ))
-    .parse(input)
-    .map_err(|op| ModParseError::ParseError(op.to_string()))?;
-
-    for item in &parsed_file.1 {
-        match item {
-            ("name", ModValue::Single(val)) => mod_descriptor.name = Some(val.to_string()),
-            ("path", ModValue::Single(val)) => mod_descriptor.path = Some(val.to_string()),
-            ("remote_file_id", ModValue::Single(val)) => {
-                mod_descriptor.remote_file_id = Some(val.to_string())
-            }
-            ("supported_version", ModValue::Single(val)) => {
-                mod_descriptor.supported_version = Some(val.to_string())
-            }
-            ("picture", ModValue::Single(val)) => mod_descriptor.picture = Some(val.to_string()),
-            ("version", ModValue::Single(val)) => mod_descriptor.version = Some(val.to_string()),
-            ("tags", ModValue::List(list)) => {
-                mod_descriptor.tags = Some(list.into_iter().map(|f| f.to_string()).collect())
-            }
-            ("dependencies", ModValue::List(list)) => {
-                mod_descriptor.dependencies =
-                    Some(list.into_iter().map(|f| f.to_string()).collect())
-            }
-            _ => {
-                log::warn!("Unknown field in mod descriptor: {}", item.0);
-            } // TODO: Probs raise an error here? Probably means we have an unexpected field?
+fn list(value: &ScriptValue) -> Option<Vec<String>> {
+    value
+        .scalar_list()
+        .map(|list| list.into_iter().map(|v| v.into_owned()).collect())
+}
+
+/// JSON form of an unmodelled value for [`ModDescriptor::extra`]: scalars
+/// become strings, keyless blocks arrays, and blocks with entries objects
+/// (last duplicate key wins, as in the game).
+fn to_json(value: &ScriptValue) -> serde_json::Value {
+    match value {
+        ScriptValue::Scalar(scalar) => serde_json::Value::String(scalar.text().into_owned()),
+        ScriptValue::Block(block) if block.entries().next().is_none() => {
+            serde_json::Value::Array(block.values().map(to_json).collect())
         }
+        ScriptValue::Block(block) => serde_json::Value::Object(
+            block
+                .entries()
+                .map(|e| (e.key.text().into_owned(), to_json(&e.value)))
+                .collect(),
+        ),
     }
+}
+
+pub fn parse_mod_file(input: &str) -> Result<ModDescriptor, ModParseError> {
+    let mod_descriptor = parse_mod_fields(input)?;
 
     // Verify required fields
     mod_descriptor
@@ -107,32 +62,320 @@ pub fn parse_mod_file(input: &str) -> Result<ModDescriptor, ModParseError> {
     Ok(mod_descriptor)
 }
 
+/// Parse a descriptor without checking for required fields. Used for the
+/// `descriptor.mod` inside a mod folder, which has no `path` of its own.
+pub fn parse_mod_fields(input: &str) -> Result<ModDescriptor, ModParseError> {
+    let mut mod_descriptor = ModDescriptor::default();
+
+    let parsed_file = script::parse_script(input)?;
+
+    for entry in parsed_file.root.entries() {
+        let value = &entry.value;
+        match entry.key.text().as_ref() {
+            "name" => mod_descriptor.name = single(value),
+            "path" => mod_descriptor.path = single(value),
+            "remote_file_id" => mod_descriptor.remote_file_id = single(value),
+            "supported_version" => mod_descriptor.supported_version = single(value),
+            "picture" => mod_descriptor.picture = single(value),
+            "version" => mod_descriptor.version = single(value),
+            "tags" => mod_descriptor.tags = list(value),
+            "dependencies" => mod_descriptor.dependencies = list(value),
+            "user_dir" => mod_descriptor.user_dir = single(value),
+            "archive" => mod_descriptor.archive = single(value),
+            // Repeats rather than overriding: one line per replaced folder.
+            "replace_path" => {
+                if let Some(folder) = single(value) {
+                    mod_descriptor
+                        .replace_path
+                        .get_or_insert_with(Vec::new)
+                        .push(folder);
+                }
+            }
+            key => {
+                mod_descriptor.extra.insert(key.to_string(), to_json(value));
+            }
+        }
+    }
+
+    Ok(mod_descriptor)
+}
+
+fn single_value(value: &Option<String>) -> Vec<ModValue<'_>> {
+    value.iter().map(|v| ModValue::Single(v)).collect()
+}
+
+fn list_value(value: &Option<Vec<String>>) -> Vec<ModValue<'_>> {
+    value.iter().map(|v| ModValue::List(v)).collect()
+}
+
+/// Every key the descriptor should contain with the value(s) it should hold,
+/// in the order a fresh descriptor is written (modelled fields in the order
+/// the Paradox launcher uses, then `extra`). Most keys have at most one
+/// value; `replace_path` has one per folder.
+fn descriptor_fields(descriptor: &ModDescriptor) -> Vec<(&str, Vec<ModValue<'_>>)> {
+    let mut fields: Vec<(&str, Vec<ModValue<'_>>)> = vec![
+        ("version", single_value(&descriptor.version)),
+        ("tags", list_value(&descriptor.tags)),
+        ("name", single_value(&descriptor.name)),
+        ("picture", single_value(&descriptor.picture)),
+        (
+            "supported_version",
+            single_value(&descriptor.supported_version),
+        ),
+        ("path", single_value(&descriptor.path)),
+        ("archive", single_value(&descriptor.archive)),
+        ("remote_file_id", single_value(&descriptor.remote_file_id)),
+        ("user_dir", single_value(&descriptor.user_dir)),
+        (
+            "replace_path",
+            descriptor
+                .replace_path
+                .iter()
+                .flatten()
+                .map(|v| ModValue::Single(v))
+                .collect(),
+        ),
+        ("dependencies", list_value(&descriptor.dependencies)),
+    ];
+    fields.extend(
+        descriptor
+            .extra
+            .iter()
+            .map(|(k, v)| (k.as_str(), vec![ModValue::Json(v)])),
+    );
+    fields
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn format_list(items: impl Iterator<Item = String>) -> String {
+    let mut out = String::from("{\n");
+    for item in items {
+        out.push('\t');
+        out.push_str(&item);
+        out.push('\n');
+    }
+    out.push('}');
+    out
+}
+
+fn format_json(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(v) => quote(v),
+        serde_json::Value::Bool(true) => "yes".to_string(),
+        serde_json::Value::Bool(false) => "no".to_string(),
+        serde_json::Value::Number(n) => n.to_string(),
+        serde_json::Value::Null => quote(""),
+        serde_json::Value::Array(items) => format_list(items.iter().map(format_json)),
+        serde_json::Value::Object(map) => {
+            format_list(map.iter().map(|(k, v)| format!("{k}={}", format_json(v))))
+        }
+    }
+}
+
+fn format_value(value: &ModValue) -> String {
+    match value {
+        ModValue::Single(v) => quote(v),
+        ModValue::List(items) => format_list(items.iter().map(|item| quote(item))),
+        ModValue::Json(v) => format_json(v),
+    }
+}
+
+/// Whether the parsed value already holds `wanted`, ignoring quoting style.
+fn value_matches(current: &ScriptValue, wanted: &ModValue) -> bool {
+    match wanted {
+        ModValue::Single(v) => current.text().is_some_and(|t| t == *v),
+        ModValue::List(items) => current
+            .scalar_list()
+            .is_some_and(|list| list.iter().eq(items.iter())),
+        ModValue::Json(v) => to_json(current) == **v,
+    }
+}
+
+/// Widen an entry's span to cover its whole line (leading indentation and the
+/// trailing newline) when nothing else shares that line, so removing it
+/// leaves no blank line behind.
+fn whole_line(input: &str, span: Range<usize>) -> Range<usize> {
+    let line_start = input[..span.start].rfind('\n').map_or(0, |i| i + 1);
+    if !input[line_start..span.start].trim().is_empty() {
+        return span;
+    }
+    let rest = &input[span.end..];
+    let line_end = rest.find('\n').map_or(input.len(), |i| span.end + i + 1);
+    if !input[span.end..line_end].trim().is_empty() {
+        return span;
+    }
+    line_start..line_end
+}
+
+/// Serialize a descriptor to a fresh `.mod` file.
+pub fn serialize_mod_file(descriptor: &ModDescriptor) -> String {
+    let mut out = String::new();
+    for (key, values) in descriptor_fields(descriptor) {
+        for value in values {
+            out.push_str(&format!("{key}={}\n", format_value(&value)));
+        }
+    }
+    out
+}
+
+/// Rewrite `original` so it describes `descriptor`, touching as little text as
+/// possible. Fields whose value is unchanged keep their exact source (quoting,
+/// layout); changed fields have only their value replaced in place; new fields
+/// are appended; fields set to `None` (or dropped from `extra`) are removed.
+/// Comments and field order are preserved.
+pub fn update_mod_file(
+    original: &str,
+    descriptor: &ModDescriptor,
+) -> Result<String, ModParseError> {
+    let parsed_file = script::parse_script(original)?;
+
+    let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+    let mut appended = String::new();
+    let fields = descriptor_fields(descriptor);
+    for (key, wanted) in &fields {
+        let existing: Vec<_> = parsed_file
+            .root
+            .entries()
+            .filter(|e| e.key.text() == *key)
+            .collect();
+        // A single-valued key only takes effect at its last occurrence, so
+        // that's the one to edit; earlier duplicates are left alone.
+        let existing = match (wanted.len(), existing.last()) {
+            (1, Some(last)) => vec![*last],
+            _ => existing,
+        };
+
+        for (current, wanted) in existing.iter().zip(wanted) {
+            if !value_matches(&current.value, wanted) {
+                edits.push((
+                    parsed_file.span_of(current.value_source),
+                    format_value(wanted),
+                ));
+            }
+        }
+        for surplus in existing.iter().skip(wanted.len()) {
+            edits.push((
+                whole_line(original, parsed_file.span_of(surplus.source)),
+                String::new(),
+            ));
+        }
+        let missing: String = wanted
+            .iter()
+            .skip(existing.len())
+            .map(|value| format!("{key}={}\n", format_value(value)))
+            .collect();
+        match existing.last() {
+            // Keep repeated keys together by inserting after the last one.
+            Some(last) if !missing.is_empty() => {
+                let end = parsed_file.span_of(last.source).end;
+                edits.push((end..end, format!("\n{}", missing.trim_end())));
+            }
+            Some(_) => {}
+            None => appended.push_str(&missing),
+        }
+    }
+
+    // Keys in the file that are neither modelled nor in `extra` anymore.
+    for entry in parsed_file.root.entries() {
+        if !fields.iter().any(|(key, _)| entry.key.text() == *key) {
+            edits.push((
+                whole_line(original, parsed_file.span_of(entry.source)),
+                String::new(),
+            ));
+        }
+    }
+
+    let mut out = original.to_string();
+    edits.sort_by_key(|(span, _)| std::cmp::Reverse(span.start));
+    for (span, replacement) in edits {
+        out.replace_range(span, &replacement);
+    }
+    if !appended.is_empty() {
+        if !out.is_empty() && !out.ends_with('\n') {
+            out.push('\n');
+        }
+        out.push_str(&appended);
+    }
+    Ok(out)
+}
+
+/// Write `descriptor` to `path`, editing the existing file in place (see
+/// [`update_mod_file`]) or creating a fresh one. Existing files are written
+/// back in the encoding they were read in. A Windows-1252 file that gains
+/// characters Windows-1252 can't hold is converted to UTF-8 instead.
+pub fn write_mod_file(path: &Path, descriptor: &ModDescriptor) -> Result<(), ModParseError> {
+    let contents = if path.exists() {
+        let original = read_text_limited(path, MAX_READ_BYTES)?;
+        let updated = update_mod_file(&original.text, descriptor).map_err(|e| e.in_file(path))?;
+        encode_text(&updated, original.encoding).unwrap_or_else(|| {
+            log::warn!(
+                "{} can no longer be stored as Windows-1252; writing it as UTF-8",
+                path.display()
+            );
+            updated.into_bytes()
+        })
+    } else {
+        serialize_mod_file(descriptor).into_bytes()
+    };
+    write_atomic(path, &contents)?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::fs;
     use std::path::Path;
 
+    fn minimal(extra: &str) -> String {
+        format!("name=\"My Mod Name\"\npath=\"mod/my_mod\"\nsupported_version=\"v4.2.*\"\n{extra}")
+    }
+
     #[test]
     fn test_parse_key_value() {
-        let input = r#"name="My Mod Name""#;
-        let (remaining, (key, value)) = parse_key_value(input).unwrap();
-        assert_eq!(key, "name");
-        assert_eq!(value, "My Mod Name");
-        assert_eq!(remaining, "");
+        let parsed_file = parse_mod_file(&minimal("")).unwrap();
+        assert_eq!(parsed_file.name.unwrap(), "My Mod Name");
+        assert_eq!(parsed_file.path.unwrap(), "mod/my_mod");
     }
 
     #[test]
     fn test_block_parser() {
-        let input = "tags={ \"Galaxy Generation\" \"Gameplay\" }";
-        let (_remaining, block_values) = parse_block_value(input).unwrap();
-        assert_eq!(block_values.0, "tags");
-        assert_eq!(block_values.1.len(), 2);
-        assert_eq!(
-            block_values.1.first().unwrap().to_string(),
-            "Galaxy Generation"
+        let input = minimal("tags={ \"Galaxy Generation\" \"Gameplay\" }");
+        let tags = parse_mod_file(&input).unwrap().tags.unwrap();
+        assert_eq!(tags.len(), 2);
+        assert_eq!(tags.first().unwrap(), "Galaxy Generation");
+        assert_eq!(tags.last().unwrap(), "Gameplay");
+    }
+
+    #[test]
+    fn test_unquoted_values_comments_and_unknown_blocks() {
+        // Each of these used to make the whole descriptor fail to parse.
+        let input = minimal(
+            "# A comment line\nversion=1.2 # trailing comment\nreplace_path=\"common/traits\"\nuser_dir={ nested={ a=b } }\n",
         );
-        assert_eq!(block_values.1.last().unwrap().to_string(), "Gameplay");
+        let parsed_file = parse_mod_file(&input).unwrap();
+        assert_eq!(parsed_file.version.unwrap(), "1.2");
+        assert_eq!(parsed_file.name.unwrap(), "My Mod Name");
+    }
+
+    #[test]
+    fn test_escaped_quotes_in_values() {
+        let input = minimal("").replace("My Mod Name", r#"The \"Best\" Mod"#);
+        let parsed_file = parse_mod_file(&input).unwrap();
+        assert_eq!(parsed_file.name.unwrap(), r#"The "Best" Mod"#);
+    }
+
+    #[test]
+    fn test_unbalanced_block_is_parse_error() {
+        let input = minimal("tags={ \"Gameplay\"");
+        let Err(ModParseError::ParseError(location)) = parse_mod_file(&input) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(location.line, 4);
+        assert_eq!(location.snippet, "tags={ \"Gameplay\"");
     }
 
     #[test]
@@ -164,4 +407,133 @@ mod tests {
             "Gameplay"
         );
     }
+
+    #[test]
+    fn test_serialize_round_trips_through_parser() {
+        let path = Path::new("./tests/fixtures/test_mod.mod");
+        let original = parse_mod_file(&fs::read_to_string(path).unwrap()).unwrap();
+        let mut with_quotes = original.clone();
+        with_quotes.name = Some(r#"A "quoted" \ name"#.to_string());
+        let reparsed = parse_mod_file(&serialize_mod_file(&with_quotes)).unwrap();
+        assert_eq!(reparsed.name, with_quotes.name);
+        assert_eq!(reparsed.tags, original.tags);
+        assert_eq!(reparsed.remote_file_id, original.remote_file_id);
+    }
+
+    #[test]
+    fn test_update_unchanged_descriptor_is_identity() {
+        let input = "# keep me\nversion=1.2\nname=\"My Mod Name\"\nunknown_key={ a=b }\npath=\"mod/x\"\nsupported_version=\"v4.2.*\"";
+        let descriptor = parse_mod_file(input).unwrap();
+        assert_eq!(update_mod_file(input, &descriptor).unwrap(), input);
+    }
+
+    #[test]
+    fn test_update_replaces_only_changed_values() {
+        let input = "# keep me\nname=\"My Mod Name\"\nreplace_path=\"common/traits\"\npath=\"/old/path\" # moved\nsupported_version=\"v4.2.*\"\n";
+        let mut descriptor = parse_mod_file(input).unwrap();
+        descriptor.path = Some("/new/path".to_string());
+        descriptor.supported_version = Some("v4.3.*".to_string());
+        assert_eq!(
+            update_mod_file(input, &descriptor).unwrap(),
+            "# keep me\nname=\"My Mod Name\"\nreplace_path=\"common/traits\"\npath=\"/new/path\" # moved\nsupported_version=\"v4.3.*\"\n"
+        );
+    }
+
+    #[test]
+    fn test_update_appends_new_and_removes_cleared_fields() {
+        let input = "name=\"My Mod Name\"\npicture=\"thumb.png\"\npath=\"mod/x\"\nsupported_version=\"v4.2.*\"";
+        let mut descriptor = parse_mod_file(input).unwrap();
+        descriptor.picture = None;
+        descriptor.dependencies = Some(vec!["Other Mod".to_string()]);
+        assert_eq!(
+            update_mod_file(input, &descriptor).unwrap(),
+            "name=\"My Mod Name\"\npath=\"mod/x\"\nsupported_version=\"v4.2.*\"\ndependencies={\n\t\"Other Mod\"\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_write_mod_file_edits_existing_file() {
+        let path =
+            std::env::temp_dir().join(format!("descriptor_write_{}.mod", std::process::id()));
+        fs::write(
+            &path,
+            "# comment\nname=\"A\"\npath=\"mod/a\"\nsupported_version=\"1\"\n",
+        )
+        .unwrap();
+        let mut descriptor = parse_mod_file(&fs::read_to_string(&path).unwrap()).unwrap();
+        descriptor.path = Some("mod/b".to_string());
+        write_mod_file(&path, &descriptor).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# comment\nname=\"A\"\npath=\"mod/b\"\nsupported_version=\"1\"\n"
+        );
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_write_mod_file_keeps_windows_1252() {
+        let path =
+            std::env::temp_dir().join(format!("descriptor_cp1252_{}.mod", std::process::id()));
+        fs::write(
+            &path,
+            b"name=\"Caf\xE9\"\npath=\"mod/a\"\nsupported_version=\"1\"\n",
+        )
+        .unwrap();
+        let mut descriptor =
+            parse_mod_file(&read_text_limited(&path, MAX_READ_BYTES).unwrap().text).unwrap();
+        descriptor.path = Some("mod/b".to_string());
+        write_mod_file(&path, &descriptor).unwrap();
+        assert_eq!(
+            fs::read(&path).unwrap(),
+            b"name=\"Caf\xE9\"\npath=\"mod/b\"\nsupported_version=\"1\"\n"
+        );
+
+        // A name Windows-1252 can't hold turns the file into UTF-8.
+        descriptor.name = Some("Caf\u{e9} \u{4e2d}".to_string());
+        write_mod_file(&path, &descriptor).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "name=\"Caf\u{e9} \u{4e2d}\"\npath=\"mod/b\"\nsupported_version=\"1\"\n"
+        );
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_unknown_and_new_fields_are_preserved() {
+        let input = minimal(
+            "replace_path=\"common/traits\"\nreplace_path=\"events\"\nuser_dir=\"my_mod_saves\"\narchive=\"mod.zip\"\nsome_game_key={ a=b c=d }\n",
+        );
+        let parsed_file = parse_mod_file(&input).unwrap();
+        assert_eq!(
+            parsed_file.replace_path.unwrap(),
+            vec!["common/traits", "events"]
+        );
+        assert_eq!(parsed_file.user_dir.unwrap(), "my_mod_saves");
+        assert_eq!(parsed_file.archive.unwrap(), "mod.zip");
+        assert_eq!(parsed_file.extra["some_game_key"]["c"], "d");
+    }
+
+    #[test]
+    fn test_update_repeated_replace_path_and_extra() {
+        let input = minimal("replace_path=\"common/traits\"\nlegacy_key=\"x\"\n");
+        let mut descriptor = parse_mod_file(&input).unwrap();
+        descriptor
+            .replace_path
+            .as_mut()
+            .unwrap()
+            .push("events".to_string());
+        descriptor.extra.remove("legacy_key");
+        descriptor
+            .extra
+            .insert("new_key".to_string(), serde_json::json!(["a"]));
+        assert_eq!(
+            update_mod_file(&input, &descriptor).unwrap(),
+            minimal(
+                "replace_path=\"common/traits\"\nreplace_path=\"events\"\nnew_key={\n\t\"a\"\n}\n"
+            )
+        );
+        let reparsed = parse_mod_file(&update_mod_file(&input, &descriptor).unwrap()).unwrap();
+        assert_eq!(reparsed.replace_path, descriptor.replace_path);
+        assert_eq!(reparsed.extra, descriptor.extra);
+    }
 }
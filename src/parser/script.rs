@@ -0,0 +1,413 @@
+use crate::errors::{ParseLocation, ScriptParseError};
+use crate::parser::diagnostic::{ParseResult, locate, required};
+use nom::{
+    Offset, Parser,
+    branch::alt,
+    bytes::complete::{is_not, tag, take_while, take_while1},
+    character::complete::{anychar, char, multispace1, space0},
+    combinator::{consumed, map, recognize, value},
+    multi::{many0, many0_count},
+    sequence::{delimited, preceded, terminated},
+};
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// Comparison operator between a key and its value. Descriptors only ever use
+/// `=`, but game script (triggers, `common/` files) uses the full set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Equals,
+    EqualsEquals,
+    NotEquals,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    /// `?=`: assign/compare only if the scope exists (Jomini games).
+    Exists,
+}
+
+/// A bare or quoted token. `raw` is the source text without surrounding
+/// quotes and with escapes left in place; use [`Scalar::text`] for the value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scalar<'a> {
+    pub raw: &'a str,
+    pub quoted: bool,
+}
+
+impl<'a> Scalar<'a> {
+    /// The scalar's value with `\"` and `\\` escapes resolved. Other
+    /// backslashes are kept as-is so Windows-style paths survive.
+    pub fn text(&self) -> Cow<'a, str> {
+        if !self.quoted || !self.raw.contains('\\') {
+            return Cow::Borrowed(self.raw);
+        }
+        let mut out = String::with_capacity(self.raw.len());
+        let mut chars = self.raw.chars();
+        while let Some(c) = chars.next() {
+            if c == '\\' {
+                match chars.next() {
+                    Some(next @ ('"' | '\\')) => out.push(next),
+                    Some(next) => {
+                        out.push('\\');
+                        out.push(next);
+                    }
+                    None => out.push('\\'),
+                }
+            } else {
+                out.push(c);
+            }
+        }
+        Cow::Owned(out)
+    }
+}
+
+/// A `{ ... }` block. Blocks hold key/value entries (objects), bare values
+/// (lists), or a mix of both. `tag` is set for tagged blocks such as
+/// `color = rgb { 255 0 0 }`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block<'a> {
+    pub tag: Option<&'a str>,
+    pub items: Vec<ScriptItem<'a>>,
+}
+
+impl<'a> Block<'a> {
+    /// Every key/value entry in the block, in source order.
+    pub fn entries(&self) -> impl Iterator<Item = &ScriptEntry<'a>> {
+        self.items.iter().filter_map(|item| match item {
+            ScriptItem::Entry(entry) => Some(entry),
+            ScriptItem::Value(_) => None,
+        })
+    }
+
+    /// Every bare (keyless) value in the block, in source order.
+    pub fn values(&self) -> impl Iterator<Item = &ScriptValue<'a>> {
+        self.items.iter().filter_map(|item| match item {
+            ScriptItem::Value(value) => Some(value),
+            ScriptItem::Entry(_) => None,
+        })
+    }
+
+    /// Value of the last entry with `key`. Paradox script lets later
+    /// assignments override earlier ones, so the last occurrence wins.
+    pub fn get(&self, key: &str) -> Option<&ScriptValue<'a>> {
+        self.entries()
+            .filter(|e| e.key.text() == key)
+            .last()
+            .map(|e| &e.value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptValue<'a> {
+    Scalar(Scalar<'a>),
+    Block(Block<'a>),
+}
+
+impl<'a> ScriptValue<'a> {
+    pub fn as_scalar(&self) -> Option<&Scalar<'a>> {
+        match self {
+            ScriptValue::Scalar(scalar) => Some(scalar),
+            ScriptValue::Block(_) => None,
+        }
+    }
+
+    pub fn as_block(&self) -> Option<&Block<'a>> {
+        match self {
+            ScriptValue::Block(block) => Some(block),
+            ScriptValue::Scalar(_) => None,
+        }
+    }
+
+    /// The value of a scalar, or `None` for a block.
+    pub fn text(&self) -> Option<Cow<'a, str>> {
+        self.as_scalar().map(Scalar::text)
+    }
+
+    /// The bare scalar values of a list block (`{ "a" "b" }`), or `None` if
+    /// this is a scalar. Nested blocks and entries are skipped.
+    pub fn scalar_list(&self) -> Option<Vec<Cow<'a, str>>> {
+        self.as_block().map(|block| {
+            block
+                .values()
+                .filter_map(|v| v.as_scalar().map(Scalar::text))
+                .collect()
+        })
+    }
+}
+
+/// `key <op> value`. `source` is the entry's full source text and
+/// `value_source` just the value's, so callers can map them back to byte
+/// ranges with [`Script::span_of`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptEntry<'a> {
+    pub key: Scalar<'a>,
+    pub operator: Operator,
+    pub value: ScriptValue<'a>,
+    pub source: &'a str,
+    pub value_source: &'a str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptItem<'a> {
+    Entry(ScriptEntry<'a>),
+    Value(ScriptValue<'a>),
+}
+
+/// A parsed script file. The top level behaves like the inside of a block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Script<'a> {
+    pub source: &'a str,
+    pub root: Block<'a>,
+}
+
+impl<'a> Script<'a> {
+    /// Byte range of a slice borrowed from this script's source (such as
+    /// [`ScriptEntry::source`]).
+    pub fn span_of(&self, slice: &str) -> Range<usize> {
+        let start = self.source.offset(slice);
+        start..start + slice.len()
+    }
+}
+
+/// Characters that end a bare token.
+fn is_bare_char(c: char) -> bool {
+    !c.is_whitespace() && !matches!(c, '=' | '<' | '>' | '!' | '?' | '{' | '}' | '#' | '"')
+}
+
+/// Whitespace and `#` line comments, in any quantity (including none).
+fn skip_trivia(input: &str) -> ParseResult<'_, &str> {
+    recognize(many0_count(alt((
+        multispace1,
+        recognize(preceded(char('#'), take_while(|c| c != '\n'))),
+    ))))
+    .parse(input)
+}
+
+/// `"..."` with `\"` and `\\` escapes; may span lines.
+fn quoted(input: &str) -> ParseResult<'_, Scalar<'_>> {
+    map(
+        delimited(
+            char('"'),
+            recognize(many0_count(alt((
+                is_not("\\\""),
+                recognize(preceded(char('\\'), anychar)),
+            )))),
+            required(char('"'), "a closing `\"`"),
+        ),
+        |raw| Scalar { raw, quoted: true },
+    )
+    .parse(input)
+}
+
+/// An unquoted token: identifiers, numbers, dates, `@variables`, and inline
+/// math such as `@[ x + 1 ]`.
+fn bare(input: &str) -> ParseResult<'_, Scalar<'_>> {
+    map(
+        alt((
+            recognize((
+                tag("@["),
+                take_while(|c| c != ']'),
+                required(char(']'), "a closing `]`"),
+            )),
+            take_while1(is_bare_char),
+        )),
+        |raw| Scalar { raw, quoted: false },
+    )
+    .parse(input)
+}
+
+fn scalar(input: &str) -> ParseResult<'_, Scalar<'_>> {
+    alt((quoted, bare)).parse(input)
+}
+
+fn operator(input: &str) -> ParseResult<'_, Operator> {
+    alt((
+        value(Operator::GreaterOrEqual, tag(">=")),
+        value(Operator::LessOrEqual, tag("<=")),
+        value(Operator::NotEquals, tag("!=")),
+        value(Operator::Exists, tag("?=")),
+        value(Operator::EqualsEquals, tag("==")),
+        value(Operator::Equals, tag("=")),
+        value(Operator::Less, tag("<")),
+        value(Operator::Greater, tag(">")),
+    ))
+    .parse(input)
+}
+
+/// Items separated by trivia, up to (not including) a closing `}` or EOF.
+fn items(input: &str) -> ParseResult<'_, Vec<ScriptItem<'_>>> {
+    preceded(skip_trivia, many0(terminated(item, skip_trivia))).parse(input)
+}
+
+fn block_body(input: &str) -> ParseResult<'_, Vec<ScriptItem<'_>>> {
+    delimited(
+        char('{'),
+        items,
+        required(char('}'), "a key, value or closing `}`"),
+    )
+    .parse(input)
+}
+
+fn script_value(input: &str) -> ParseResult<'_, ScriptValue<'_>> {
+    alt((
+        map(block_body, |items| {
+            ScriptValue::Block(Block { tag: None, items })
+        }),
+        // Tagged block (`rgb { ... }`); the tag must share a line with the brace
+        // so a bare value followed by a block on the next line isn't merged.
+        map(
+            (take_while1(is_bare_char), space0, block_body),
+            |(tag, _, items)| {
+                ScriptValue::Block(Block {
+                    tag: Some(tag),
+                    items,
+                })
+            },
+        ),
+        map(scalar, ScriptValue::Scalar),
+    ))
+    .parse(input)
+}
+
+fn entry(input: &str) -> ParseResult<'_, ScriptEntry<'_>> {
+    map(
+        consumed((
+            scalar,
+            delimited(skip_trivia, operator, skip_trivia),
+            consumed(required(script_value, "a value")),
+        )),
+        |(source, (key, operator, (value_source, value)))| ScriptEntry {
+            key,
+            operator,
+            value,
+            source,
+            value_source,
+        },
+    )
+    .parse(input)
+}
+
+fn item(input: &str) -> ParseResult<'_, ScriptItem<'_>> {
+    alt((
+        map(entry, ScriptItem::Entry),
+        map(script_value, ScriptItem::Value),
+    ))
+    .parse(input)
+}
+
+/// Parse a Paradox (Clausewitz/Jomini) script file into a borrowed tree.
+///
+/// Handles quoted and unquoted scalars, nested and tagged blocks, `#`
+/// comments, and the full operator set. The whole input must parse; an
+/// unbalanced brace or stray token is an error rather than a silent stop.
+pub fn parse_script(input: &str) -> Result<Script<'_>, ScriptParseError> {
+    let (rest, root_items) =
+        items(input).map_err(|e| ScriptParseError::ParseError(locate(input, e)))?;
+    if !rest.is_empty() {
+        return Err(ScriptParseError::ParseError(ParseLocation::at(
+            input,
+            input.offset(rest),
+            "a key or value",
+        )));
+    }
+    Ok(Script {
+        source: input,
+        root: Block {
+            tag: None,
+            items: root_items,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_blocks_and_operators() {
+        let input = r#"
+            my_trait = {
+                cost = 2
+                potential = { age >= 18 NOT = { has_trait = foo } }
+            }
+        "#;
+        let script = parse_script(input).unwrap();
+        let my_trait = script.root.get("my_trait").unwrap().as_block().unwrap();
+        assert_eq!(my_trait.get("cost").unwrap().text().unwrap(), "2");
+
+        let potential = my_trait.get("potential").unwrap().as_block().unwrap();
+        let age = potential.entries().next().unwrap();
+        assert_eq!(age.key.text(), "age");
+        assert_eq!(age.operator, Operator::GreaterOrEqual);
+        assert_eq!(age.value.text().unwrap(), "18");
+        let not = potential.get("NOT").unwrap().as_block().unwrap();
+        assert_eq!(not.get("has_trait").unwrap().text().unwrap(), "foo");
+    }
+
+    #[test]
+    fn test_lists_and_tagged_blocks() {
+        let input = r#"tags = { "Gameplay" Balance } color = rgb { 255 0 0 }"#;
+        let script = parse_script(input).unwrap();
+        let tags = script.root.get("tags").unwrap().scalar_list().unwrap();
+        assert_eq!(tags, vec!["Gameplay", "Balance"]);
+
+        let color = script.root.get("color").unwrap().as_block().unwrap();
+        assert_eq!(color.tag, Some("rgb"));
+        assert_eq!(color.values().count(), 3);
+    }
+
+    #[test]
+    fn test_comments_and_escaped_quotes() {
+        let input = "# header\nname = \"a \\\"quoted\\\" #name\" # trailing\n";
+        let script = parse_script(input).unwrap();
+        assert_eq!(
+            script.root.get("name").unwrap().text().unwrap(),
+            "a \"quoted\" #name"
+        );
+    }
+
+    #[test]
+    fn test_last_assignment_wins() {
+        let script = parse_script("version = 1 version = 2").unwrap();
+        assert_eq!(script.root.get("version").unwrap().text().unwrap(), "2");
+    }
+
+    #[test]
+    fn test_spans_map_back_to_source() {
+        let input = "a = 1\nb = { c = \"d\" }\n";
+        let script = parse_script(input).unwrap();
+        let b = script.root.entries().nth(1).unwrap();
+        assert_eq!(&input[script.span_of(b.source)], "b = { c = \"d\" }");
+        assert_eq!(&input[script.span_of(b.value_source)], "{ c = \"d\" }");
+    }
+
+    fn error_location(input: &str) -> ParseLocation {
+        match parse_script(input) {
+            Err(ScriptParseError::ParseError(location)) => location,
+            Ok(_) => panic!("expected {input:?} to fail"),
+        }
+    }
+
+    #[test]
+    fn test_unbalanced_braces_fail() {
+        let missing_close = error_location("a = {\n  b = c\n");
+        assert_eq!((missing_close.line, missing_close.column), (3, 1));
+        assert!(missing_close.expected.contains('}'));
+
+        let stray_close = error_location("a = b\nc = d }");
+        assert_eq!((stray_close.line, stray_close.column), (2, 7));
+        assert_eq!(stray_close.snippet, "c = d }");
+    }
+
+    #[test]
+    fn test_error_points_at_missing_value_and_quote() {
+        let missing_value = error_location("name = \"x\"\nversion =\n}");
+        assert_eq!((missing_value.line, missing_value.column), (3, 1));
+        assert_eq!(missing_value.expected, "a value");
+
+        let open_quote = error_location("name = \"never closed");
+        assert_eq!(open_quote.line, 1);
+        assert!(open_quote.expected.contains('"'));
+    }
+}
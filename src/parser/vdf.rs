@@ -1,118 +1,250 @@
-use crate::errors::VdfParseError;
+use crate::errors::{ParseLocation, VdfParseError};
 use crate::models::LibraryVdf;
+use crate::parser::diagnostic::{ParseResult, locate, required};
 use nom::{
-    IResult, Parser,
+    Offset, Parser,
     branch::alt,
-    bytes::complete::take_while,
-    character::complete::{char, multispace0, multispace1},
-    combinator::{map, recognize},
-    multi::many0,
-    sequence::{delimited, preceded, separated_pair},
+    bytes::complete::{is_not, tag, take_while, take_while1},
+    character::complete::{anychar, char, multispace1},
+    combinator::{map, opt, recognize},
+    multi::{many0, many0_count},
+    sequence::{delimited, preceded, terminated},
 };
+use std::borrow::Cow;
 
-#[derive(Debug)]
-pub enum VdfEntry<'a> {
-    KeyValue(&'a str, &'a str),
-    Block(&'a str, Vec<(&'a str, &'a str)>),
+/// A KeyValues value: a string or a nested block of entries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VdfValue<'a> {
+    Str(Cow<'a, str>),
+    Block(Vec<VdfEntry<'a>>),
 }
 
-pub fn parse_quoted_string(input: &str) -> IResult<&str, &str> {
-    delimited(char('"'), take_while(|c| c != '"'), char('"')).parse(input)
+impl<'a> VdfValue<'a> {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            VdfValue::Str(s) => Some(s),
+            VdfValue::Block(_) => None,
+        }
+    }
+
+    pub fn as_block(&self) -> Option<&[VdfEntry<'a>]> {
+        match self {
+            VdfValue::Block(entries) => Some(entries),
+            VdfValue::Str(_) => None,
+        }
+    }
+
+    /// Value of the first entry named `key` in a block. Keys are matched
+    /// case-insensitively, as Steam does (`"StateFlags"` vs `"stateflags"`).
+    pub fn get(&self, key: &str) -> Option<&VdfValue<'a>> {
+        self.as_block()?
+            .iter()
+            .find(|e| e.key.eq_ignore_ascii_case(key))
+            .map(|e| &e.value)
+    }
+
+    /// Follow a path of keys through nested blocks.
+    pub fn get_path(&self, path: &[&str]) -> Option<&VdfValue<'a>> {
+        path.iter().try_fold(self, |value, key| value.get(key))
+    }
 }
 
-pub fn parse_tabbed_kv_pair(input: &str) -> IResult<&str, (&str, &str)> {
-    separated_pair(parse_quoted_string, multispace1, parse_quoted_string).parse(input)
+/// `"key" value [$CONDITION]`. `key_source` is the key as written (without
+/// quotes) so errors about it can be located in the file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VdfEntry<'a> {
+    pub key: Cow<'a, str>,
+    pub value: VdfValue<'a>,
+    /// Platform conditional such as `$WIN32` or `!$X360`, without brackets.
+    /// Left for the caller to evaluate.
+    pub condition: Option<&'a str>,
+    pub key_source: &'a str,
 }
 
-pub fn parse_apps_block(input: &str) -> IResult<&str, (&str, Vec<(&str, &str)>)> {
-    separated_pair(
-        parse_quoted_string,
-        multispace1,
-        delimited(
-            char('{'),
-            many0(preceded(multispace0, parse_tabbed_kv_pair)),
-            preceded(multispace0, char('}')),
-        ),
-    )
-    .parse(input)
+/// A parsed KeyValues text file (`libraryfolders.vdf`, `appmanifest_*.acf`,
+/// `localconfig.vdf`, ...). The top level is a list of entries, normally a
+/// single named root block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vdf<'a> {
+    pub source: &'a str,
+    pub entries: Vec<VdfEntry<'a>>,
 }
 
-pub fn parse_vdf_block(input: &str) -> Result<LibraryVdf, VdfParseError> {
-    let (_, (vdf_idx, entries)) = separated_pair(
-        parse_quoted_string,
-        multispace1,
-        delimited(
-            char('{'),
-            many0(preceded(
-                multispace0,
-                alt((
-                    map(parse_apps_block, |(k, v)| VdfEntry::Block(k, v)),
-                    map(parse_tabbed_kv_pair, |(k, v)| VdfEntry::KeyValue(k, v)),
-                )),
-            )),
-            preceded(multispace0, char('}')),
-        ),
-    )
-    .parse(input)
-    .map_err(|e| VdfParseError::ParseError(e.to_string()))?;
+impl<'a> Vdf<'a> {
+    /// The root block's value, e.g. the contents of `"AppState" { ... }`.
+    pub fn root(&self) -> Option<&VdfValue<'a>> {
+        self.entries.first().map(|e| &e.value)
+    }
 
-    let mut path: Option<String> = None;
-    let mut apps: Vec<u32> = vec![];
-    let idx = vdf_idx.parse::<u32>().map_err(|e| {
-        VdfParseError::ParseError(format!("Invalid VDF index '{}': {}", vdf_idx, e))
-    })?;
+    /// Point at a slice borrowed from this file, such as a `key_source`.
+    pub fn locate(&self, slice: &str, expected: &str) -> ParseLocation {
+        ParseLocation::at(self.source, self.source.offset(slice), expected)
+    }
+}
 
-    for entry in entries {
-        match entry {
-            VdfEntry::KeyValue("path", value) => {
-                path = Some(value.to_string());
-            }
-            VdfEntry::Block("apps", app_list) => {
-                for (app_id, _size) in app_list {
-                    // Size of games is not currently used
-                    apps.push(app_id.parse()?);
-                }
+/// Resolve KeyValues escapes (`\n`, `\t`, `\\`, `\"`). Unknown escapes are
+/// kept verbatim.
+fn unescape(raw: &str) -> Cow<'_, str> {
+    if !raw.contains('\\') {
+        return Cow::Borrowed(raw);
+    }
+    let mut out = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some(next @ ('\\' | '"')) => out.push(next),
+            Some(next) => {
+                out.push('\\');
+                out.push(next);
             }
-            _ => {}
+            None => out.push('\\'),
         }
     }
+    Cow::Owned(out)
+}
 
-    let path = path.ok_or(VdfParseError::MissingField("path".to_string()))?;
+/// Whitespace and `//` line comments, in any quantity (including none).
+fn skip_trivia(input: &str) -> ParseResult<'_, &str> {
+    recognize(many0_count(alt((
+        multispace1,
+        recognize(preceded(tag("//"), take_while(|c| c != '\n'))),
+    ))))
+    .parse(input)
+}
 
-    let lib_vdf = LibraryVdf { idx, path, apps };
-    Ok(lib_vdf)
+/// The raw (still escaped) contents of a `"..."` string.
+pub fn parse_quoted_string(input: &str) -> ParseResult<'_, &str> {
+    delimited(
+        char('"'),
+        recognize(many0_count(alt((
+            is_not("\\\""),
+            recognize(preceded(char('\\'), anychar)),
+        )))),
+        required(char('"'), "a closing `\"`"),
+    )
+    .parse(input)
 }
 
-fn parse_raw_block(input: &str) -> IResult<&str, &str> {
-    recognize(separated_pair(
+/// A quoted or bare token, returning its raw text.
+fn token(input: &str) -> ParseResult<'_, &str> {
+    alt((
         parse_quoted_string,
-        multispace1,
-        delimited(
-            char('{'),
-            many0(preceded(
-                multispace0,
-                alt((recognize(parse_apps_block), recognize(parse_tabbed_kv_pair))),
-            )),
-            preceded(multispace0, char('}')),
+        take_while1(|c: char| !c.is_whitespace() && !matches!(c, '{' | '}' | '"' | '[' | ']')),
+    ))
+    .parse(input)
+}
+
+/// `[$WIN32]`-style platform conditional.
+fn condition(input: &str) -> ParseResult<'_, &str> {
+    delimited(char('['), is_not("]"), required(char(']'), "a closing `]`")).parse(input)
+}
+
+fn entries(input: &str) -> ParseResult<'_, Vec<VdfEntry<'_>>> {
+    preceded(skip_trivia, many0(terminated(parse_entry, skip_trivia))).parse(input)
+}
+
+fn value(input: &str) -> ParseResult<'_, VdfValue<'_>> {
+    alt((
+        map(
+            delimited(
+                char('{'),
+                entries,
+                required(char('}'), "a key or closing `}`"),
+            ),
+            VdfValue::Block,
         ),
+        map(token, |raw| VdfValue::Str(unescape(raw))),
     ))
     .parse(input)
 }
 
-pub fn parse_vdf_file(input: &str) -> Result<Vec<LibraryVdf>, VdfParseError> {
-    let (_, (_, raw_blocks)) = separated_pair(
-        parse_quoted_string,
-        multispace1,
-        delimited(
-            char('{'),
-            many0(preceded(multispace0, parse_raw_block)),
-            preceded(multispace0, char('}')),
+/// One `key value` pair, where the value may be a nested block.
+pub fn parse_entry(input: &str) -> ParseResult<'_, VdfEntry<'_>> {
+    map(
+        (
+            token,
+            preceded(skip_trivia, required(value, "a value or `{`")),
+            opt(preceded(take_while(|c| c == ' ' || c == '\t'), condition)),
         ),
+        |(key_source, value, condition)| VdfEntry {
+            key: unescape(key_source),
+            value,
+            condition,
+            key_source,
+        },
     )
     .parse(input)
-    .map_err(|e| VdfParseError::ParseError(e.to_string()))?;
+}
+
+/// Parse a KeyValues text file into a borrowed tree. Handles arbitrary
+/// nesting, quoted and bare tokens, escapes, `//` comments and `[$COND]`
+/// conditionals; the whole input must parse.
+pub fn parse_keyvalues(input: &str) -> Result<Vdf<'_>, VdfParseError> {
+    let (rest, entries) =
+        entries(input).map_err(|e| VdfParseError::ParseError(locate(input, e)))?;
+    if !rest.is_empty() {
+        return Err(VdfParseError::ParseError(ParseLocation::at(
+            input,
+            input.offset(rest),
+            "a key",
+        )));
+    }
+    Ok(Vdf {
+        source: input,
+        entries,
+    })
+}
+
+/// Build one library from a `"<idx>" { "path" ... "apps" { ... } }` entry.
+fn library_from_entry(vdf: &Vdf, entry: &VdfEntry) -> Result<LibraryVdf, VdfParseError> {
+    let idx = entry.key.parse::<u32>().map_err(|_| {
+        VdfParseError::ParseError(vdf.locate(entry.key_source, "a numeric library index"))
+    })?;
+    let path = entry
+        .value
+        .get("path")
+        .and_then(VdfValue::as_str)
+        .ok_or(VdfParseError::MissingField("path".to_string()))?
+        .to_string();
+    let mut apps: Vec<u32> = vec![];
+    for app in entry
+        .value
+        .get("apps")
+        .and_then(VdfValue::as_block)
+        .unwrap_or_default()
+    {
+        // Size of games is not currently used
+        apps.push(app.key.parse()?);
+    }
+    Ok(LibraryVdf { idx, path, apps })
+}
 
-    raw_blocks.into_iter().map(parse_vdf_block).collect()
+pub fn parse_vdf_block(input: &str) -> Result<LibraryVdf, VdfParseError> {
+    let vdf = parse_keyvalues(input)?;
+    let entry = vdf
+        .entries
+        .first()
+        .ok_or(VdfParseError::MissingField("library".to_string()))?;
+    library_from_entry(&vdf, entry)
+}
+
+/// Every library in a `libraryfolders.vdf`. Non-block entries under the root
+/// (older files carry stats ids there) are skipped.
+pub fn parse_vdf_file(input: &str) -> Result<Vec<LibraryVdf>, VdfParseError> {
+    let vdf = parse_keyvalues(input)?;
+    vdf.root()
+        .and_then(VdfValue::as_block)
+        .unwrap_or_default()
+        .iter()
+        .filter(|entry| entry.value.as_block().is_some())
+        .map(|entry| library_from_entry(&vdf, entry))
+        .collect()
 }
 
 #[cfg(test)]
@@ -131,10 +263,10 @@ mod tests {
     #[test]
     fn test_tabbed_key_value_pair() {
         let input = r#""test_key"            "test_value""#;
-        let (remaining, result) = parse_tabbed_kv_pair(input).unwrap();
+        let (remaining, result) = parse_entry(input).unwrap();
         assert_eq!(remaining, "");
-        assert_eq!(result.0, "test_key");
-        assert_eq!(result.1, "test_value")
+        assert_eq!(result.key, "test_key");
+        assert_eq!(result.value.as_str(), Some("test_value"))
     }
 
     #[test]
@@ -145,12 +277,15 @@ mod tests {
 			"12345"		"12345454534"
 		}"#;
 
-        let (remaining, result) = parse_apps_block(input).unwrap();
+        let (remaining, result) = parse_entry(input).unwrap();
         assert_eq!(remaining, "");
-        assert_eq!(result.0, "apps");
-        assert_eq!(result.1.len(), 2);
-        assert_eq!(result.1[0], ("123", "1234567345"));
-        assert_eq!(result.1[1], ("12345", "12345454534"));
+        assert_eq!(result.key, "apps");
+        let apps = result.value.as_block().unwrap();
+        assert_eq!(apps.len(), 2);
+        assert_eq!(apps[0].key, "123");
+        assert_eq!(apps[0].value.as_str(), Some("1234567345"));
+        assert_eq!(apps[1].key, "12345");
+        assert_eq!(apps[1].value.as_str(), Some("12345454534"));
     }
 
     #[test]
@@ -189,4 +324,52 @@ mod tests {
         assert_eq!(libraries[1].path, "/run/media/user/drive/alt_steam_loc");
         assert_eq!(libraries[1].apps, vec![7, 8, 9, 10, 11, 12]);
     }
+
+    #[test]
+    fn test_vdf_error_reports_line_and_column() {
+        let input = "\"libraryfolders\"\n{\n\t\"0\"\n\t{\n\t\t\"path\" \"/x\"\n";
+        let Err(VdfParseError::ParseError(location)) = parse_vdf_file(input) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(location.line, 6);
+        assert!(location.expected.contains('}'));
+    }
+
+    #[test]
+    fn test_vdf_bad_index_points_at_index() {
+        let input = "\"libraryfolders\"\n{\n\t\"abc\"\n\t{\n\t\t\"path\" \"/x\"\n\t}\n}";
+        let Err(VdfParseError::ParseError(location)) = parse_vdf_file(input) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!((location.line, location.column), (3, 3));
+        assert_eq!(location.snippet, "\"abc\"");
+    }
+
+    #[test]
+    fn test_keyvalues_nesting_escapes_comments_and_conditionals() {
+        let input = r#"// leading comment
+"UserLocalConfigStore"
+{
+    "Software" { "Valve" { "Steam" { "apps" { "281990" {
+        "LaunchOptions"   "-debug \"quoted\"\tend" // trailing
+        "LastPlayed"      1700000000
+    } } } } }
+    "Windows only"   "yes"   [$WIN32]
+    "path"           "C:\\Games"
+}"#;
+        let vdf = parse_keyvalues(input).unwrap();
+        let root = vdf.root().unwrap();
+        let app = root
+            .get_path(&["Software", "Valve", "Steam", "apps", "281990"])
+            .unwrap();
+        assert_eq!(
+            app.get("launchoptions").unwrap().as_str(),
+            Some("-debug \"quoted\"\tend")
+        );
+        assert_eq!(app.get("LastPlayed").unwrap().as_str(), Some("1700000000"));
+
+        let entries = root.as_block().unwrap();
+        assert_eq!(entries[1].condition, Some("$WIN32"));
+        assert_eq!(root.get("path").unwrap().as_str(), Some("C:\\Games"));
+    }
 }
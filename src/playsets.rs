@@ -0,0 +1,273 @@
+use crate::errors::LauncherDbError;
+use crate::games::{GameRegistry, LauncherKind};
+use crate::models::{DetectedGame, ModCollection, ModEntry};
+use rusqlite::{Connection, OptionalExtension, params};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// The Paradox launcher's database. The launcher keeps its own playsets (load
+/// orders) there and rewrites `dlc_load.json` from the active one on start, so
+/// a collection only sticks if the playset is updated too. Mods are matched
+/// between the two by the id `ModDescriptor::mod_id` uses: the Steam workshop
+/// id, or the mod folder for local mods.
+///
+/// `None` for games started through another launcher (Victoria 3's Dowser),
+/// which keeps no playsets there.
+pub fn launcher_db_path(game: &DetectedGame, registry: &GameRegistry) -> Option<PathBuf> {
+    let launcher = registry
+        .get(game.app_id)
+        .map(|definition| definition.launcher)
+        .unwrap_or_default();
+    (launcher == LauncherKind::ParadoxLauncher)
+        .then(|| Path::new(&game.paradox_data_path).join("launcher-v2.sqlite"))
+}
+
+/// Every playset the launcher hasn't deleted, as collections keeping the
+/// playset's id, name, order and enabled flags.
+pub fn import_playsets(db_path: &Path) -> Result<Vec<ModCollection>, LauncherDbError> {
+    let conn = Connection::open(db_path)?;
+    let mut playsets = conn.prepare(
+        "SELECT id, name FROM playsets WHERE isRemoved = 0 OR isRemoved IS NULL ORDER BY createdOn, name",
+    )?;
+    let mut mods = conn.prepare(
+        "SELECT COALESCE(NULLIF(m.steamId, ''), m.dirPath), pm.enabled
+         FROM playsets_mods pm JOIN mods m ON m.id = pm.modId
+         WHERE pm.playsetId = ?1
+         ORDER BY pm.position",
+    )?;
+
+    let rows = playsets
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut collections = Vec::new();
+    for (id, name) in rows {
+        let entries = mods
+            .query_map([&id], |row| {
+                Ok((
+                    row.get::<_, Option<String>>(0)?,
+                    row.get::<_, Option<bool>>(1)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        collections.push(ModCollection {
+            id: Uuid::parse_str(&id).map_err(|_| LauncherDbError::InvalidId(id.clone()))?,
+            name,
+            mods: entries
+                .into_iter()
+                .filter_map(|(mod_id, enabled)| {
+                    Some(ModEntry {
+                        mod_id: mod_id?,
+                        enabled: enabled.unwrap_or(true),
+                    })
+                })
+                .collect(),
+            disabled_dlcs: None,
+        });
+    }
+    Ok(collections)
+}
+
+/// Write a collection into the launcher as the playset with the same id,
+/// replacing its mod list and order. With `activate`, it also becomes the
+/// launcher's active playset.
+///
+/// The launcher only knows mods it has registered itself, so entries it has
+/// no row for can't be placed; their ids are returned and the rest of the
+/// playset is still written. Local mods are matched by folder: the launcher
+/// stores it absolute, while descriptors usually give it relative to the
+/// game's data folder, which is where the database lives.
+pub fn export_playset(
+    db_path: &Path,
+    collection: &ModCollection,
+    activate: bool,
+) -> Result<Vec<String>, LauncherDbError> {
+    let data_dir = db_path.parent().unwrap_or(Path::new(""));
+    let mut conn = Connection::open(db_path)?;
+    let tx = conn.transaction()?;
+    let playset_id = collection.id.to_string();
+
+    let exists = tx
+        .query_row(
+            "SELECT 1 FROM playsets WHERE id = ?1",
+            [&playset_id],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some();
+    if exists {
+        tx.execute(
+            "UPDATE playsets SET name = ?2, isRemoved = 0, updatedOn = datetime('now') WHERE id = ?1",
+            params![playset_id, collection.name],
+        )?;
+    } else {
+        tx.execute(
+            "INSERT INTO playsets (id, name, isActive, loadOrder, createdOn, isRemoved)
+             VALUES (?1, ?2, 0, 'custom', datetime('now'), 0)",
+            params![playset_id, collection.name],
+        )?;
+    }
+    tx.execute(
+        "DELETE FROM playsets_mods WHERE playsetId = ?1",
+        [&playset_id],
+    )?;
+
+    let mut unregistered = Vec::new();
+    {
+        let mut find_mod = tx.prepare(
+            "SELECT id FROM mods WHERE NULLIF(steamId, '') = ?1 OR dirPath = ?2 LIMIT 1",
+        )?;
+        let mut insert = tx.prepare(
+            "INSERT INTO playsets_mods (playsetId, modId, position, enabled) VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        let mut position = 0;
+        for entry in &collection.mods {
+            let dir_path = data_dir.join(&entry.mod_id);
+            let launcher_id: Option<String> = find_mod
+                .query_row(params![entry.mod_id, dir_path.to_string_lossy()], |row| {
+                    row.get(0)
+                })
+                .optional()?;
+            let Some(launcher_id) = launcher_id else {
+                unregistered.push(entry.mod_id.clone());
+                continue;
+            };
+            insert.execute(params![playset_id, launcher_id, position, entry.enabled])?;
+            position += 1;
+        }
+    }
+
+    if activate {
+        tx.execute("UPDATE playsets SET isActive = (id = ?1)", [&playset_id])?;
+    }
+    tx.commit()?;
+    Ok(unregistered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const FIXTURE: &str = "tests/fixtures/launcher/launcher-v2.sqlite";
+
+    /// Exports write to the database, so work on a private copy.
+    fn scratch_db(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("ferrous_{name}_{}.sqlite", std::process::id()));
+        fs::copy(FIXTURE, &path).unwrap();
+        path
+    }
+
+    fn make_game(app_id: u32) -> DetectedGame {
+        DetectedGame::for_test(app_id, "/data")
+    }
+
+    #[test]
+    fn test_launcher_db_path_follows_the_games_launcher() {
+        let registry = GameRegistry::embedded();
+        assert_eq!(
+            launcher_db_path(&make_game(281990), &registry),
+            Some(PathBuf::from("/data/launcher-v2.sqlite"))
+        );
+        // Victoria 3 starts through Dowser, which has no playsets.
+        assert_eq!(launcher_db_path(&make_game(529340), &registry), None);
+    }
+
+    #[test]
+    fn test_import_playsets() {
+        let collections = import_playsets(Path::new(FIXTURE)).unwrap();
+        let names: Vec<_> = collections.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["Main", "Vanilla+"]);
+
+        let main = &collections[0];
+        assert_eq!(main.id.to_string(), "b0000000-0000-0000-0000-000000000001");
+        let order: Vec<_> = main
+            .mods
+            .iter()
+            .map(|m| (m.mod_id.as_str(), m.enabled))
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                (
+                    "/home/user/.local/share/Paradox Interactive/Stellaris/mod/local",
+                    true
+                ),
+                ("1001", false),
+                ("1002", true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_export_playset_round_trips_and_activates() {
+        let db = scratch_db("export_playset");
+        let mut collection = ModCollection::new("From Ferrous");
+        collection.add_mod("1002".to_string());
+        collection.add_mod("1001".to_string());
+        collection.add_mod("99999".to_string());
+
+        let unregistered = export_playset(&db, &collection, true).unwrap();
+        assert_eq!(unregistered, vec!["99999"]);
+
+        let imported = import_playsets(&db).unwrap();
+        let exported = imported.iter().find(|c| c.id == collection.id).unwrap();
+        assert_eq!(exported.name, "From Ferrous");
+        let ids: Vec<_> = exported.mods.iter().map(|m| m.mod_id.as_str()).collect();
+        assert_eq!(ids, vec!["1002", "1001"]);
+
+        let conn = Connection::open(&db).unwrap();
+        let active: String = conn
+            .query_row("SELECT id FROM playsets WHERE isActive = 1", [], |r| {
+                r.get(0)
+            })
+            .unwrap();
+        assert_eq!(active, collection.id.to_string());
+        let _ = fs::remove_file(&db);
+    }
+
+    #[test]
+    fn test_export_playset_replaces_existing_order() {
+        let db = scratch_db("replace_playset");
+        let mut main = import_playsets(&db).unwrap().remove(0);
+        main.mods.reverse();
+        main.mods.pop();
+
+        assert!(export_playset(&db, &main, false).unwrap().is_empty());
+        let reimported = import_playsets(&db).unwrap().remove(0);
+        let ids: Vec<_> = reimported.mods.iter().map(|m| m.mod_id.as_str()).collect();
+        assert_eq!(ids, vec!["1002", "1001"]);
+        let _ = fs::remove_file(&db);
+    }
+
+    #[test]
+    fn test_export_playset_matches_relative_local_mods() {
+        let data_dir =
+            std::env::temp_dir().join(format!("ferrous_local_playset_{}", std::process::id()));
+        fs::create_dir_all(&data_dir).unwrap();
+        let db = data_dir.join("launcher-v2.sqlite");
+        fs::copy(FIXTURE, &db).unwrap();
+        Connection::open(&db)
+            .unwrap()
+            .execute(
+                "INSERT INTO mods (id, dirPath, status, source, createdDate)
+                 VALUES ('m-mine', ?1, 'ready_to_play', 'local', datetime('now'))",
+                [data_dir.join("mod/mine").to_string_lossy()],
+            )
+            .unwrap();
+
+        let mut collection = ModCollection::new("Local");
+        collection.add_mod("mod/mine".to_string());
+        collection.add_mod("1001".to_string());
+        assert!(export_playset(&db, &collection, false).unwrap().is_empty());
+        let exported = import_playsets(&db)
+            .unwrap()
+            .into_iter()
+            .find(|c| c.id == collection.id)
+            .unwrap();
+        assert_eq!(exported.mods.len(), 2);
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+}
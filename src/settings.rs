@@ -0,0 +1,170 @@
+use crate::errors::FileOperationError;
+use crate::fsutil::{MAX_READ_BYTES, read_to_string_limited, write_atomic};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// User settings for finding games and mods the default locations miss: Snap
+/// or custom Steam installs, a second Steam install, a moved Documents
+/// folder, or mods kept somewhere else entirely. Everything listed here is
+/// searched in addition to the defaults, never instead of them.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Settings {
+    /// Steam install folders (the ones containing `steamapps/`). A path to a
+    /// `libraryfolders.vdf` also works.
+    #[serde(default)]
+    pub steam_roots: Vec<PathBuf>,
+    /// Folders like `Documents/Paradox Interactive`, holding one data folder
+    /// per game.
+    #[serde(default)]
+    pub paradox_roots: Vec<PathBuf>,
+    /// Extra folders mod content may live under. Mods pointing anywhere else
+    /// outside Steam and Paradox folders are not scanned (see `ModRoots`).
+    #[serde(default)]
+    pub mod_roots: Vec<PathBuf>,
+}
+
+impl Settings {
+    /// The saved settings, or the defaults when there are none. A broken file
+    /// is logged and ignored so detection keeps working.
+    pub fn load() -> Self {
+        let path = crate::locations::settings_path();
+        if !path.exists() {
+            return Self::default();
+        }
+        Self::load_from(&path).unwrap_or_else(|e| {
+            log::warn!("Ignoring settings {}: {e}", path.display());
+            Self::default()
+        })
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self, FileOperationError> {
+        let contents = read_to_string_limited(path, MAX_READ_BYTES)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    /// Check the mod roots before they're saved: each must be an existing
+    /// folder, and neither `/` nor the home folder, which would let every
+    /// descriptor path through `ModRoots`.
+    pub fn validate(&self) -> Result<(), FileOperationError> {
+        self.validate_for_home(dirs::home_dir().as_deref())
+    }
+
+    fn validate_for_home(&self, home: Option<&Path>) -> Result<(), FileOperationError> {
+        let home = home.and_then(|h| h.canonicalize().ok());
+        self.mod_roots
+            .iter()
+            .try_for_each(|root| check_mod_root(root, home.as_deref()))
+    }
+
+    /// The mod roots that pass [`Settings::validate`]'s checks against
+    /// `home`. A settings file written by hand or by an older version may
+    /// hold unsafe ones; those are logged and left out.
+    pub fn safe_mod_roots(&self, home: &Path) -> Vec<PathBuf> {
+        let home = home.canonicalize().ok();
+        self.mod_roots
+            .iter()
+            .filter(|root| match check_mod_root(root, home.as_deref()) {
+                Ok(()) => true,
+                Err(e) => {
+                    log::warn!("Ignoring mod root: {e}");
+                    false
+                }
+            })
+            .cloned()
+            .collect()
+    }
+
+    pub fn save(&self) -> Result<(), FileOperationError> {
+        self.save_to(&crate::locations::settings_path())
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<(), FileOperationError> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        write_atomic(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Reject a mod root that's missing, not a folder, the filesystem root or the
+/// (canonical) home folder.
+fn check_mod_root(root: &Path, home: Option<&Path>) -> Result<(), FileOperationError> {
+    let invalid = |reason: &str| FileOperationError::InvalidModRoot {
+        path: root.to_path_buf(),
+        reason: reason.to_string(),
+    };
+    let canonical = root
+        .canonicalize()
+        .map_err(|_| invalid("it doesn't exist"))?;
+    if !canonical.is_dir() {
+        return Err(invalid("it isn't a folder"));
+    }
+    if canonical.parent().is_none() {
+        return Err(invalid("it's the filesystem root"));
+    }
+    if home == Some(canonical.as_path()) {
+        return Err(invalid("it's the home folder"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_round_trip_and_partial_files() {
+        let path = std::env::temp_dir()
+            .join(format!("ferrous_settings_test_{}", std::process::id()))
+            .join("settings.json");
+        let settings = Settings {
+            steam_roots: vec![PathBuf::from("/mnt/games/Steam")],
+            paradox_roots: vec![],
+            mod_roots: vec![PathBuf::from("/srv/mods")],
+        };
+        settings.save_to(&path).unwrap();
+        assert_eq!(Settings::load_from(&path).unwrap(), settings);
+
+        std::fs::write(&path, r#"{ "mod_roots": ["/srv/mods"] }"#).unwrap();
+        let partial = Settings::load_from(&path).unwrap();
+        assert!(partial.steam_roots.is_empty());
+        assert_eq!(partial.mod_roots, vec![PathBuf::from("/srv/mods")]);
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_validate_rejects_unsafe_mod_roots() {
+        let base = std::env::temp_dir().join(format!("ferrous_mod_root_{}", std::process::id()));
+        let home = base.join("home");
+        std::fs::create_dir_all(home.join("mods")).unwrap();
+        std::fs::write(base.join("file"), "").unwrap();
+        let with_root = |root: PathBuf| Settings {
+            mod_roots: vec![root],
+            ..Default::default()
+        };
+
+        assert!(
+            with_root(home.join("mods"))
+                .validate_for_home(Some(&home))
+                .is_ok()
+        );
+        for root in [
+            base.join("missing"),
+            base.join("file"),
+            PathBuf::from("/"),
+            home.clone(),
+            home.join("mods/.."),
+        ] {
+            assert!(
+                matches!(
+                    with_root(root.clone()).validate_for_home(Some(&home)),
+                    Err(FileOperationError::InvalidModRoot { .. })
+                ),
+                "{} should be rejected",
+                root.display()
+            );
+        }
+        let _ = std::fs::remove_dir_all(&base);
+    }
+}
@@ -0,0 +1,3 @@
+﻿name="BOM Mod"
+path="mod/bom_mod"
+supported_version="v4.2.*"
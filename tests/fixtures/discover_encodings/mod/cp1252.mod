@@ -0,0 +1,3 @@
+name="Caf Mod"
+path="mod/cafe_mod"
+supported_version="v4.2.*"
@@ -0,0 +1,3 @@
+name="Merged Mod"
+path="mod/merged"
+supported_version="1.5.*"
@@ -0,0 +1,3 @@
+name="Local"
+path="mod/local"
+supported_version="3.*"
@@ -0,0 +1,4 @@
+name="Up To Date"
+path="mod/up_to_date"
+remote_file_id="1001"
+supported_version="3.*"
@@ -0,0 +1,4 @@
+name="Stale"
+path="mod/stale"
+remote_file_id="1002"
+supported_version="3.*"
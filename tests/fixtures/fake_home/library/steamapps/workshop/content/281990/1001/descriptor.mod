@@ -0,0 +1,2 @@
+name="Up To Date"
+supported_version="3.*"
@@ -0,0 +1,7 @@
+version="1.0"
+tags={
+	"Gameplay"
+}
+name="Fresh Subscription"
+supported_version="v3.12.*"
+remote_file_id="2001"
@@ -0,0 +1 @@
+supported_version="v3.12.*"
@@ -0,0 +1,2 @@
+name="Unversioned"
+remote_file_id="2004"
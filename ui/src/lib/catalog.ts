@@ -3,7 +3,8 @@
 // (`app-ui/src/main.rs` `category_of`/`cat_meta`/status classification), adapted
 // to the data the Tauri backend actually exposes.
 
-import type { ModConflict, ResolvedMod } from './types';
+import type { ModConflict, ResolvedMod, VanillaOverride } from './types';
+import { workshopLabel } from './types';
 
 // ---------------------------------------------------------------------------
 // Categories (bucketed from Paradox `tags`)
@@ -149,6 +150,7 @@ export interface DecoratedMod {
   mod_id: string;
   name: string;
   version: string; // "v1.2" or ""
+  workshopStatus: string; // "Needs update", "Updated 1/2/2025", or "" for local mods
   tags: string[]; // raw Paradox tags, drives the sidebar tag filter
   category: CategoryKey;
   catLabel: string;
@@ -166,6 +168,8 @@ export interface DecoratedMod {
   statusBg: string;
   hasIssue: boolean; // conflict or dependency problem
   issueText: string;
+  /** Game files the mod replaces (enabled mods only). */
+  vanillaOverrides: VanillaOverride[];
 }
 
 /** Lookups shared across every mod in a single decoration pass. */
@@ -183,6 +187,8 @@ export interface DecorateContext {
   enabledNames: Set<string>;
   /** mod name -> conflicts it participates in (already filtered to enabled mods). */
   conflictsByName: Map<string, ModConflict[]>;
+  /** mod name -> game files it replaces (already filtered to enabled mods). */
+  vanillaByName: Map<string, VanillaOverride[]>;
 }
 
 export function decorateMod(mod: ResolvedMod, ctx: DecorateContext): DecoratedMod {
@@ -205,8 +211,11 @@ export function decorateMod(mod: ResolvedMod, ctx: DecorateContext): DecoratedMo
     if (cs && cs.length > 0) {
       conflictActive = true;
       const c = cs[0];
-      const other = c.mod_list.find((n) => n !== name) ?? '(unknown)';
-      issueText = `Conflicts with ${other} — ${c.file_path}`;
+      if (c.winner === name) {
+        issueText = `Overrides ${c.overridden.join(', ')} — ${c.file_path}`;
+      } else {
+        issueText = `Overridden by ${c.winner} — ${c.file_path}`;
+      }
     }
   }
 
@@ -244,6 +253,7 @@ export function decorateMod(mod: ResolvedMod, ctx: DecorateContext): DecoratedMo
     mod_id: mod.mod_id,
     name,
     version: mod.version ? `v${mod.version}` : '',
+    workshopStatus: workshopLabel(mod.workshop),
     tags: mod.tags ?? [],
     category: cat,
     catLabel: meta.label,
@@ -261,5 +271,6 @@ export function decorateMod(mod: ResolvedMod, ctx: DecorateContext): DecoratedMo
     statusBg: sMeta.bg,
     hasIssue: status === 'conflict' || status === 'dep',
     issueText,
+    vanillaOverrides: enabled ? (ctx.vanillaByName.get(name) ?? []) : [],
   };
 }
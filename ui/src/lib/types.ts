@@ -3,6 +3,23 @@ export interface DetectedGame {
   install_path: string;
   game_name: string;
   paradox_data_path: string;
+  state_flags: number;
+  build_id?: number | null;
+  last_updated?: number | null;
+  /** The libraryfolders.vdf the game was found through. */
+  source?: LocationSource | null;
+}
+
+/** A Steam, Paradox or mod location: a default one, or one added in settings. */
+export interface LocationSource {
+  kind: 'default' | 'settings';
+  path: string;
+}
+
+export interface Settings {
+  steam_roots: string[];
+  paradox_roots: string[];
+  mod_roots: string[];
 }
 
 export interface ModDescriptor {
@@ -14,6 +31,60 @@ export interface ModDescriptor {
   picture?: string;
   version?: string;
   dependencies?: string[];
+  replace_path?: string[];
+  user_dir?: string;
+  archive?: string;
+  descriptor_file?: string | null;
+  metadata_id?: string | null;
+  workshop?: WorkshopItem | null;
+  /** The mod root the content was found under. */
+  source?: LocationSource | null;
+}
+
+export type WorkshopState = 'Installed' | 'NeedsUpdate' | 'Downloading';
+
+export interface WorkshopItem {
+  item_id: string;
+  state: WorkshopState;
+  size?: number | null;
+  time_updated?: number | null;
+  manifest?: string | null;
+}
+
+// Short Steam download status for a workshop mod; empty for local mods.
+export function workshopLabel(item: WorkshopItem | null | undefined): string {
+  if (!item) return '';
+  switch (item.state) {
+    case 'NeedsUpdate':
+      return 'Needs update';
+    case 'Downloading':
+      return 'Downloading';
+    case 'Installed':
+      return item.time_updated
+        ? `Updated ${new Date(item.time_updated * 1000).toLocaleDateString()}`
+        : 'Installed';
+  }
+}
+
+export interface ParseLocation {
+  file?: string | null;
+  line: number;
+  column: number;
+  snippet: string;
+  expected: string;
+}
+
+export interface DescriptorDiagnostic {
+  file: string;
+  message: string;
+  location?: ParseLocation | null;
+}
+
+export interface ModDiscovery {
+  mods: ModDescriptor[];
+  diagnostics: DescriptorDiagnostic[];
+  // Steam's workshop state file exists but couldn't be read.
+  workshop_error: string | null;
 }
 
 export interface ModEntry {
@@ -25,6 +96,37 @@ export interface ModCollection {
   id: string;
   name: string;
   mods: ModEntry[];
+  /** `.dlc` paths to disable on apply; null leaves the game's selection alone. */
+  disabled_dlcs?: string[] | null;
+}
+
+export interface DlcDescriptor {
+  file: string;
+  name: string;
+  steam_id: string | null;
+  category: string | null;
+  affects_checksum: boolean;
+}
+
+/** A game file snapshotted before the manager overwrote it. */
+export interface Backup {
+  id: string;
+  /** Relative to the game's Paradox data folder, e.g. "dlc_load.json". */
+  file: string;
+  created_ms: number;
+}
+
+export type DiffLine =
+  | { Same: string }
+  | { Added: string }
+  | { Removed: string }
+  | { Binary: { identical: boolean } }
+  | { TooLarge: { old_lines: number; new_lines: number } };
+
+export interface DlcLoadImport {
+  collection: ModCollection;
+  /** dlc_load.json entries that matched no installed mod. */
+  unresolved: string[];
 }
 
 export interface MissingDependency {
@@ -52,10 +154,91 @@ export type ConflictCategory =
   | 'Sound'
   | 'Other';
 
+export type ConflictKind = 'FileOverride' | 'DirectoryReplaced';
+
+/** How the mods' copies of the file compare; fully identical copies aren't reported. */
+export type ContentOverlap = 'Identical' | 'Differing' | 'PartiallyIdentical';
+
 export interface ModConflict {
   file_path: string;
   mod_list: string[];
   category: ConflictCategory;
+  kind: ConflictKind;
+  replaced_by?: string | null;
+  /** The mod whose copy the game uses. */
+  winner: string;
+  /** The other mods in mod_list, whose copies are lost. */
+  overridden: string[];
+  /** Whether the winner is the last mod the collection loads, not just the last in mod_list. */
+  winner_is_last: boolean;
+  content?: ContentOverlap | null;
+}
+
+/** A mod file that replaces one of the game's own files. */
+export interface VanillaOverride {
+  file_path: string;
+  category: ConflictCategory;
+  mod: string;
+  /** The game's copy changed after the mod's, e.g. in a patch. */
+  vanilla_newer: boolean;
+  /** The mod doesn't ship the file: its replace_path deletes the game's copy. */
+  wiped: boolean;
+}
+
+export interface ConflictReport {
+  conflicts: ModConflict[];
+  vanilla_overrides: VanillaOverride[];
+}
+
+export type OverrideRule = 'LastWins' | 'FirstWins' | 'FileReplace';
+
+export interface ObjectDefinition {
+  mod: string;
+  file: string;
+}
+
+/** An object (trait, event id, define, ...) that several mods define. */
+export interface ObjectConflict {
+  folder: string;
+  key: string;
+  category: ConflictCategory;
+  rule: OverrideRule;
+  /** Every mod's definition, in load order. */
+  definitions: ObjectDefinition[];
+  winner: string;
+  overridden: string[];
+}
+
+export interface LocalisationDefinition {
+  mod: string;
+  file: string;
+  value: string;
+}
+
+/** A localisation key several mods define for one language. */
+export interface LocalisationConflict {
+  language: string;
+  key: string;
+  definitions: LocalisationDefinition[];
+  winner: string;
+  winning_value: string;
+}
+
+export interface LanguageCoverage {
+  language: string;
+  /** Keys the mod has in english but not in this language. */
+  missing: string[];
+}
+
+export interface ModLocalisationCoverage {
+  mod: string;
+  english_keys: number;
+  languages: LanguageCoverage[];
+}
+
+export interface LocalisationReport {
+  conflicts: LocalisationConflict[];
+  coverage: ModLocalisationCoverage[];
 }
 
 export type ConflictSeverity = 'none' | 'low' | 'medium' | 'high';
@@ -65,6 +248,10 @@ const MEDIUM_CATEGORIES: Set<ConflictCategory> = new Set(['Defines', 'Other']);
 const HOTNESS_THRESHOLD = 5;
 
 export function conflictSeverityForFile(conflict: ModConflict): ConflictSeverity {
+  // A replace_path wipes every earlier mod's files in the folder.
+  if (conflict.kind === 'DirectoryReplaced') return 'high';
+  // Some mods ship the same copy, so part of the overlap is harmless.
+  if (conflict.content === 'PartiallyIdentical') return 'low';
   const isHot = conflict.mod_list.length >= HOTNESS_THRESHOLD;
   if (isHot) return 'low';
   if (HIGH_CATEGORIES.has(conflict.category)) return 'high';